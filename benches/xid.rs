@@ -0,0 +1,79 @@
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+fn new_id(c: &mut Criterion) {
+    let g = libxid::new_generator();
+
+    c.bench_function("new_id", |b| b.iter(|| g.new_id().unwrap()));
+}
+
+// Every thread generates `iters / threads` ids from the same generator, the
+// measured time is the wall clock time of the whole batch.
+fn new_id_contended(c: &mut Criterion) {
+    let mut group = c.benchmark_group("new_id_contended");
+
+    for threads in [2, 4, 8] {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(threads),
+            &threads,
+            |b, &threads| {
+                b.iter_custom(|iters| {
+                    let g = Arc::new(libxid::new_generator());
+                    let per_thread = (iters / threads).max(1);
+
+                    let start = Instant::now();
+
+                    let handles: Vec<_> = (0..threads)
+                        .map(|_| {
+                            let g = Arc::clone(&g);
+
+                            thread::spawn(move || {
+                                for _ in 0..per_thread {
+                                    black_box(g.new_id().unwrap());
+                                }
+                            })
+                        })
+                        .collect();
+
+                    for h in handles {
+                        h.join().unwrap();
+                    }
+
+                    start.elapsed()
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+fn encode(c: &mut Criterion) {
+    let id = libxid::new_generator().new_id().unwrap();
+
+    c.bench_function("encode", |b| b.iter(|| black_box(id).encode()));
+
+    let mut buff = [0u8; 20];
+
+    c.bench_function("encode_into", |b| {
+        b.iter(|| black_box(id).encode_into(&mut buff))
+    });
+}
+
+fn decode(c: &mut Criterion) {
+    let encoded = libxid::new_generator().new_id().unwrap().encode();
+
+    c.bench_function("decode", |b| {
+        b.iter(|| libxid::ID::decode(black_box(&encoded)).unwrap())
+    });
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().measurement_time(Duration::from_secs(3));
+    targets = new_id, new_id_contended, encode, decode
+}
+criterion_main!(benches);
@@ -18,8 +18,11 @@
 //! Xid doesn't use base64 because case sensitivity and the 2 non alphanum chars may be an
 //! issue when transported as a string between various systems. Base36 wasn't retained either
 //! because 1/ it's not standard 2/ the resulting size is not predictable (not bit aligned)
-//! and 3/ it would not remain sortable. To validate a base32 `xid`, expect a 20 chars long,
-//! all lowercase sequence of `a` to `v` letters and `0` to `9` numbers (`[0-9a-v]{20}`).
+//! and 3/ it would not remain sortable. A valid base32 `xid`, as checked by `is_valid`, is a 20
+//! chars long sequence of `a` to `v` lowercase letters and `0` to `9` numbers
+//! (`[0-9a-v]{20}`), whose last char is `0` or `g` as it only carries 1 bit. This canonical
+//! form is the only one sorting like the ids, uppercase ids (see `encode_upper`) are only
+//! accepted by `ID::decode_with`.
 //!
 //! UUIDs are 16 bytes (128 bits) and 36 chars as string representation. Twitter Snowflake
 //! ids are 8 bytes (64 bits) but require machine/data-center configuration and/or central
@@ -53,9 +56,11 @@
 //! Notes:
 //!
 //! - Xid is dependent on the system time, a monotonic counter and so is not cryptographically secure.
-//! If unpredictability of IDs is important, you should NOT use xids.
-//! It is worth noting that most of the other UUID like implementations are also not cryptographically secure.
-//! You shoud use libraries that rely on cryptographically secure sources if you want a truly random ID generator.
+//!   If unpredictability of IDs is important, you should NOT use xids.
+//!   It is worth noting that most of the other UUID like implementations are also not cryptographically secure.
+//!   You shoud use libraries that rely on cryptographically secure sources if you want a truly random ID generator.
+//!   `Generator::new_random_id()` keeps the xid format and timestamp but fills the other 8 bytes from the OS random
+//!   source, trading the unicity guarantee for unpredictability.
 //!
 //! References:
 //!
@@ -66,10 +71,15 @@
 //! ## Usage
 //!
 //! ```rust
+//! # #[cfg(feature = "std")]
+//! # {
 //! use libxid;
 //!
-//! // initialize it once, reuse it afterwards
-//! let mut g = libxid::new_generator();
+//! // a process wide generator is used by `libxid::new_id()`
+//! let id = libxid::new_id().unwrap();
+//!
+//! // or initialize one once, reuse it afterwards, it can be shared between threads
+//! let g = libxid::new_generator();
 //!
 //! for i in 0..10{
 //!     let id = g.new_id().unwrap();
@@ -82,98 +92,885 @@
 //!             id.time()
 //!     );
 //! }
+//! # }
+//! ```
+//!
+//! ### Cargo features
+//!
+//! - `serde`: implements `Serialize` and `Deserialize` for `ID` and `IdList`, ids are serialized
+//!   as their 20 chars base32 string in human readable formats (JSON, YAML...) and as their 12
+//!   raw bytes in binary formats (bincode, CBOR...)
+//! - `std` (enabled by default): machine id and pid auto-detection, `SystemTime` based APIs. Without
+//!   it the crate is `no_std` (it still needs `alloc`), generators must be configured with
+//!   `GeneratorBuilder::try_build`, which fails unless the machine id and pid are set, and ids
+//!   generated with `Generator::new_id_with_timestamp`. Generators need 64 bits atomics, they are
+//!   emulated with `portable-atomic` on targets which lack them (e.g. `thumbv7m-none-eabi`). Targets without compare-and-swap at all (e.g. `thumbv6m-none-eabi`) also
+//!   need its `critical-section` feature or its `portable_atomic_unsafe_assume_single_core` cfg
+//! - `chrono`: adds `ID::storage_key`
+//! - `uuid`: adds `ID::to_uuid` and `ID::from_uuid`, the id is stored in the first 12 bytes of the
+//!   `Uuid` followed by 4 zero bytes
+//!
+//! ### Machine id
+//!
+//! The machine id is derived from the platform machine id (`/etc/machine-id` on Linux,
+//! `IOPlatformUUID` on macOS, `MachineGuid` on Windows) or from the hostname. It can be pinned
+//! with the `XID_MACHINE_ID` environment variable, set to 6 hexadecimal digits (e.g.
+//! `XID_MACHINE_ID=0a1b2c`), any other value is ignored.
+//!
+//! ### WebAssembly
+//!
+//! On `wasm32-unknown-unknown` (browsers, edge workers) the time comes from `Date.now()` and the
+//! OS random source from `crypto.getRandomValues()`, through `wasm-bindgen`. There is no machine id
+//! nor process id to detect there, so each generator gets a random machine id
+//! (`Generator::machine_id_is_random()` is true) and a random pid, unless they are set with
+//! `GeneratorBuilder`. `Generator::new_id_throttled` is not available there, as threads can not sleep.
+//!
+//! ### Sub-second ordering
+//!
+//! The embedded time has a 1 second precision and there is no millisecond mode: the 4 bytes
+//! timestamp is what keeps ids compatible with Mongo Object IDs. Ids generated by the same
+//! `Generator` still sort deterministically in generation order, even microseconds apart, as the
+//! counter of ids sharing a timestamp is strictly increasing (when it wraps around the timestamp
+//! is carried to the next second). Ids of different generators sharing a second are ordered by
+//! machine id and pid before the counter, so they have no sub-second ordering.
+//!
+//! ### Ids as map keys
+//!
+//! Ids are hashable and ordered, as they are k-ordered a `BTreeMap` keyed by
+//! ids iterates in creation order and supports range queries. Ids compare and
+//! hash like their bytes, and implement `Borrow<[u8; 12]>`, so maps keyed by
+//! ids can be queried with raw bytes too:
+//!
+//! ```rust
+//! # #[cfg(feature = "std")]
+//! # {
+//! use std::collections::BTreeMap;
+//!
+//! let g = libxid::new_generator();
+//!
+//! let mut map = BTreeMap::new();
+//!
+//! let ids: Vec<libxid::ID> = (0..10).map(|_| g.new_id().unwrap()).collect();
+//!
+//! for (i, id) in ids.iter().enumerate().rev() {
+//!     map.insert(*id, i);
+//! }
+//!
+//! assert_eq!(map.values().cloned().collect::<Vec<usize>>(), (0..10).collect::<Vec<usize>>());
+//! assert_eq!(map.range(ids[2]..ids[5]).count(), 3);
+//! assert_eq!(map.get(ids[2].as_bytes()), Some(&2));
+//! # }
 //! ```
 //!
 //! [Olivier Poitrey]: https://github.com/rs
 //! [xid]: https://github.com/rs/xid
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
 extern crate byteorder;
+#[cfg(feature = "std")]
 extern crate crc32fast;
+#[cfg(all(
+    feature = "std",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
 extern crate gethostname;
 extern crate md5;
+extern crate portable_atomic;
+#[cfg(feature = "std")]
 extern crate rand;
-
+#[cfg(all(feature = "std", windows))]
+extern crate winreg;
+
+use alloc::collections::{BTreeMap, BTreeSet, BinaryHeap};
+#[cfg(not(feature = "std"))]
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
 use byteorder::{BigEndian, ByteOrder};
+use core::borrow::Borrow;
+use core::cmp::Reverse;
+use core::convert::TryFrom;
+use core::fmt;
+use core::str::FromStr;
+#[cfg(all(
+    feature = "std",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
 use crc32fast::Hasher;
+#[cfg(all(
+    feature = "std",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
 use gethostname::*;
+// emulates the 64 bits atomics on targets which lack them (e.g. thumbv7m)
+use portable_atomic::{AtomicU16, AtomicU64, Ordering};
+#[cfg(feature = "std")]
 use rand::prelude::*;
+#[cfg(feature = "serde")]
 use serde::de::{self, Visitor};
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use std::error::Error;
-use std::fmt;
+#[cfg(feature = "std")]
+use std::env;
+#[cfg(feature = "std")]
+use std::error::Error as StdError;
+#[cfg(all(
+    feature = "std",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
 use std::fs;
-use std::fs::File;
+#[cfg(feature = "std")]
 use std::io;
+#[cfg(feature = "std")]
 use std::io::prelude::*;
+#[cfg(all(
+    feature = "std",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
 use std::process;
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+#[cfg(feature = "std")]
+use std::sync::OnceLock;
+#[cfg(feature = "std")]
+use std::time::{Duration, SystemTime, SystemTimeError, UNIX_EPOCH};
+#[cfg(all(feature = "std", windows))]
+use winreg::{enums::*, RegKey};
 
 const ID_LEN: usize = 12;
 
+// number of distinct values of the 24 bits counter
+const COUNTER_MAX: u64 = 1 << 24;
+
+// carry value of a generator which has not generated any id yet
+const FRESH: u64 = 0xFF;
+
+// number of seconds a generator can borrow from the future once the counter
+// wrapped around within a second
+const MAX_CARRY: u64 = FRESH - 1;
+
+const ENCODING: &[u8; 32] = b"0123456789abcdefghijklmnopqrstuv";
+
+// Maps each byte to its value in `ENCODING`, 0xFF marks bytes outside of the
+// alphabet.
+const DECODING: [u8; 256] = decoding_table();
+
+const fn decoding_table() -> [u8; 256] {
+    let mut dec = [0xFFu8; 256];
+
+    let mut i = 0;
+    while i < ENCODING.len() {
+        dec[ENCODING[i] as usize] = i as u8;
+        i += 1;
+    }
+
+    dec
+}
+
 // ---
 
+/// Errors returned when generating ids. New variants may be added, or become
+/// available with the `std` feature.
 #[derive(Clone, Debug)]
-pub struct IDGenerationError(String);
+#[non_exhaustive]
+pub enum Error {
+    /// the system clock (or the given time) is before the Unix epoch
+    #[cfg(feature = "std")]
+    SystemTime(SystemTimeError),
+    /// the counter wrapped around too many times within a second, and no more
+    /// seconds can be borrowed from the future
+    CounterExhausted,
+    /// the end of the requested range is before its start
+    InvalidRange,
+    /// the OS random source is not available, see `Generator::new_random_id`
+    Rng,
+}
+
+/// Former name of `Error`, kept for backward compatibility.
+pub type IDGenerationError = Error;
+
+#[cfg(feature = "std")]
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Error::SystemTime(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            #[cfg(feature = "std")]
+            Error::SystemTime(e) => write!(f, "{}", e),
+            Error::CounterExhausted => write!(f, "counter exhausted for the current second"),
+            Error::InvalidRange => write!(f, "end is before start"),
+            Error::Rng => write!(f, "os random source unavailable"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<SystemTimeError> for Error {
+    fn from(e: SystemTimeError) -> Error {
+        Error::SystemTime(e)
+    }
+}
+
+/// Errors returned by `new_generator_checked()` and `GeneratorBuilder::try_build`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum GeneratorInitError {
+    /// neither the platform machine id nor the hostname are available, a
+    /// random machine id would be used. Without the `std` feature: no machine
+    /// id was set
+    MachineIdUnavailable,
+    /// /proc/self/cpuset could not be read on Linux, the raw process id would
+    /// be used
+    PidContainerCheckFailed,
+    /// no pid was set, it can not be detected without the `std` feature
+    PidUnavailable,
+}
 
-impl Error for IDGenerationError {
-    fn description(&self) -> &str {
-        self.0.as_str()
+#[cfg(feature = "std")]
+impl StdError for GeneratorInitError {}
+
+impl fmt::Display for GeneratorInitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GeneratorInitError::MachineIdUnavailable => {
+                write!(f, "machine id could not be detected")
+            },
+            GeneratorInitError::PidContainerCheckFailed => {
+                write!(f, "could not read /proc/self/cpuset")
+            },
+            GeneratorInitError::PidUnavailable => write!(f, "pid not set"),
+        }
+    }
+}
+
+/// Errors returned when parsing an id from its string or Mongo Object ID form.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DecodeError {
+    /// the input does not have the expected length, in bytes
+    InvalidLength { expected: usize, actual: usize },
+    /// the character starting at this byte position is not part of the
+    /// alphabet (`[0-9a-v]` for xids, hexadecimal digits for Mongo Object IDs)
+    InvalidCharacter { position: usize, character: char },
+    /// the unused trailing bits of the last char are set, so the input would
+    /// not encode back to itself
+    NonCanonical,
+    /// the embedded time is outside of the accepted window, see
+    /// `ID::from_bytes_validated`
+    TimestampOutOfRange,
+}
+
+impl DecodeError {
+    // `position` must be a char boundary of `input`, which holds for the
+    // first invalid byte of an otherwise ASCII prefix
+    fn invalid_character(input: &str, position: usize) -> DecodeError {
+        DecodeError::InvalidCharacter {
+            position,
+            character: input[position..].chars().next().unwrap_or('\u{FFFD}'),
+        }
     }
 }
 
-impl fmt::Display for IDGenerationError {
+#[cfg(feature = "std")]
+impl StdError for DecodeError {}
+
+impl fmt::Display for DecodeError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.0)
+        match self {
+            DecodeError::InvalidLength { expected, actual } => {
+                write!(
+                    f,
+                    "invalid length: expected {} bytes, got {}",
+                    expected, actual
+                )
+            },
+            DecodeError::InvalidCharacter {
+                position,
+                character,
+            } => write!(
+                f,
+                "invalid character {:?} at position {}",
+                character, position
+            ),
+            DecodeError::NonCanonical => write!(f, "non canonical encoding"),
+            DecodeError::TimestampOutOfRange => write!(f, "timestamp out of range"),
+        }
     }
 }
 
 // ---
 
+/// Generators are `Send + Sync` and lock-free: a single generator can be shared
+/// between threads (e.g. behind an `Arc`) without any `Mutex`.
 pub struct Generator {
-    counter: AtomicUsize,
+    // timestamp (upper 32 bits), number of seconds the timestamp was carried
+    // ahead of the requested one (8 bits) and counter (lower 24 bits) of the
+    // latest generated id, see `reserve`
+    state: AtomicU64,
     machine_id: [u8; 3],
-    pid: u32,
+    // the machine id could not be detected and was drawn at random
+    machine_id_random: bool,
+    // atomic so that `refresh_pid` can update a shared generator
+    pid: AtomicU16,
+    // total number of generated ids, see `ids_generated`
+    generated: AtomicU64,
+    // latest timestamp used by `new_id`, including the carried ones
+    #[cfg(feature = "std")]
+    last_time: AtomicU64,
+}
+
+/// Cloning forks the generator: the copy starts from the current counter value
+/// and keeps the machine id and pid, so both generators produce the same ids
+/// unless the machine id or pid of one of them is changed (see
+/// `with_machine_id` and `with_pid_bytes`). Meant for controlled forking in
+/// tests and sharded workloads.
+impl Clone for Generator {
+    fn clone(&self) -> Generator {
+        Generator {
+            state: AtomicU64::new(self.state.load(Ordering::SeqCst)),
+            machine_id: self.machine_id,
+            machine_id_random: self.machine_id_random,
+            pid: AtomicU16::new(self.pid()),
+            generated: AtomicU64::new(0),
+            #[cfg(feature = "std")]
+            last_time: AtomicU64::new(self.last_time.load(Ordering::SeqCst)),
+        }
+    }
 }
 
+/// Generator with an auto-detected machine id and pid, and a random initial
+/// counter.
+#[cfg(feature = "std")]
 pub fn new_generator() -> Generator {
-    Generator {
-        counter: rand_int(),
-        machine_id: read_machine_id(),
-        pid: get_pid(),
+    GeneratorBuilder::new().build()
+}
+
+/// Generates an id with a process wide generator, created by `new_generator()`
+/// on first use, like `xid.New()` in Go. Use `new_generator()` to get
+/// independent generators.
+#[cfg(feature = "std")]
+pub fn new_id() -> Result<ID, Error> {
+    static GLOBAL: OnceLock<Generator> = OnceLock::new();
+
+    GLOBAL.get_or_init(new_generator).new_id()
+}
+
+/// Same as `new_generator()` but fails instead of silently falling back to a
+/// random machine id or, on Linux, to the raw process id when the container
+/// cpuset can not be read.
+#[cfg(feature = "std")]
+pub fn new_generator_checked() -> Result<Generator, GeneratorInitError> {
+    GeneratorBuilder::new().try_build()
+}
+
+/// Machine id derived from a name (e.g. a node name) the same way the
+/// auto-detected one is derived from the platform machine id or hostname: the
+/// first 3 bytes of its md5 hash.
+pub fn machine_id_from_str(s: &str) -> [u8; 3] {
+    let hash = md5::compute(s);
+
+    [hash[0], hash[1], hash[2]]
+}
+
+/// Builds a `Generator`, every field which is not explicitly set is
+/// auto-detected like `new_generator()` does. Without the `std` feature there is
+/// no auto-detection: only `try_build` is available, and it fails unless the
+/// machine id and pid are set.
+#[derive(Clone, Debug, Default)]
+pub struct GeneratorBuilder {
+    machine_id: Option<[u8; 3]>,
+    pid: Option<u16>,
+    counter: Option<u32>,
+}
+
+impl GeneratorBuilder {
+    /// Builder with no field set.
+    pub fn new() -> GeneratorBuilder {
+        GeneratorBuilder::default()
+    }
+
+    /// Machine id written in every id, instead of the auto-detected one.
+    pub fn machine_id(mut self, id: [u8; 3]) -> GeneratorBuilder {
+        self.machine_id = Some(id);
+        self
+    }
+
+    /// Pid written in every id, instead of the auto-detected one.
+    pub fn pid(mut self, pid: u16) -> GeneratorBuilder {
+        self.pid = Some(pid);
+        self
+    }
+
+    /// Initial counter value, only the lower 24 bits are used.
+    pub fn counter(mut self, counter: u32) -> GeneratorBuilder {
+        self.counter = Some(counter);
+        self
+    }
+
+    /// Same as `counter`.
+    pub fn counter_start(self, counter: u32) -> GeneratorBuilder {
+        self.counter(counter)
+    }
+
+    /// Auto-detects the unset fields, falling back to a random machine id
+    /// when none can be detected (see `Generator::machine_id_is_random`).
+    #[cfg(feature = "std")]
+    pub fn build(self) -> Generator {
+        self.build_with_rng(&mut thread_rng())
+    }
+
+    /// Same as `build` but fails, like `new_generator_checked()`, instead of
+    /// falling back to a random machine id or to the raw process id when the
+    /// unset fields can not be detected.
+    #[cfg(feature = "std")]
+    pub fn try_build(self) -> Result<Generator, GeneratorInitError> {
+        let machine_id = match self.machine_id {
+            Some(id) => id,
+            None => detect_machine_id().ok_or(GeneratorInitError::MachineIdUnavailable)?,
+        };
+
+        let pid = match self.pid {
+            Some(pid) => pid,
+            None => {
+                fold_pid(checked_pid().map_err(|_| GeneratorInitError::PidContainerCheckFailed)?)
+            },
+        };
+
+        Ok(self.machine_id(machine_id).pid(pid).build())
+    }
+
+    /// Same as `build`, the initial counter and the machine id fallback (when
+    /// nothing can be detected) are drawn from `rng`.
+    #[cfg(feature = "std")]
+    pub fn build_with_rng<R: RngCore>(self, rng: &mut R) -> Generator {
+        let (machine_id, machine_id_random) =
+            read_machine_id(self.machine_id.or_else(detect_machine_id), rng);
+
+        Generator::with_fields(
+            machine_id,
+            machine_id_random,
+            self.pid.unwrap_or_else(get_pid),
+            self.counter.unwrap_or_else(|| rand_int(rng)),
+        )
     }
+
+    /// Fails unless the machine id and the pid are set, there is no
+    /// auto-detection without the `std` feature (and no `build`, which would
+    /// silently give colliding ids to every unconfigured process). The counter
+    /// starts at zero unless set.
+    #[cfg(not(feature = "std"))]
+    pub fn try_build(self) -> Result<Generator, GeneratorInitError> {
+        let machine_id = self
+            .machine_id
+            .ok_or(GeneratorInitError::MachineIdUnavailable)?;
+        let pid = self.pid.ok_or(GeneratorInitError::PidUnavailable)?;
+
+        Ok(Generator::with_fields(
+            machine_id,
+            false,
+            pid,
+            self.counter.unwrap_or(0),
+        ))
+    }
+}
+
+fn initial_state(counter: u32) -> u64 {
+    FRESH << 24 | u64::from(counter) & (COUNTER_MAX - 1)
 }
 
 impl Generator {
-    pub fn new_id(&self) -> Result<ID, IDGenerationError> {
-        self.new_id_with_time(SystemTime::now())
+    fn with_fields(
+        machine_id: [u8; 3],
+        machine_id_random: bool,
+        pid: u16,
+        counter: u32,
+    ) -> Generator {
+        Generator {
+            state: AtomicU64::new(initial_state(counter)),
+            machine_id,
+            machine_id_random,
+            pid: AtomicU16::new(pid),
+            generated: AtomicU64::new(0),
+            #[cfg(feature = "std")]
+            last_time: AtomicU64::new(0),
+        }
+    }
+
+    /// Same as `GeneratorBuilder::new()`, e.g.
+    /// `Generator::builder().machine_id([1, 2, 3]).pid(4).build()`.
+    pub fn builder() -> GeneratorBuilder {
+        GeneratorBuilder::new()
+    }
+
+    /// Derives the machine id, pid and initial counter from `key`, two
+    /// generators built from the same key produce the same ids for the same
+    /// timestamps. Meant for reproducible tests, not for production.
+    pub fn from_seed(key: u64) -> Generator {
+        let mut buff = [0u8; 8];
+        BigEndian::write_u64(&mut buff, key);
+
+        let hash = md5::compute(buff);
+
+        Generator::with_fields(
+            [hash[0], hash[1], hash[2]],
+            false,
+            BigEndian::read_u16(&[hash[3], hash[4]]),
+            u32::from(hash[5]) << 16 | u32::from(hash[6]) << 8 | u32::from(hash[7]),
+        )
+    }
+
+    /// Auto-detects the machine id and pid like `new_generator()`, randomness
+    /// comes from `rng` so that a seeded rng gives reproducible generators.
+    #[cfg(feature = "std")]
+    pub fn with_rng<R: RngCore>(rng: &mut R) -> Generator {
+        GeneratorBuilder::new().build_with_rng(rng)
+    }
+
+    /// Auto-detects the machine id like `new_generator()` but uses `pid`
+    /// verbatim, without reading the process id nor the container cpuset (whose
+    /// checksum may be shared by all the pods of a node), e.g. with a value
+    /// from a pod annotation. Same as `GeneratorBuilder::new().pid(pid)`.
+    #[cfg(feature = "std")]
+    pub fn with_pid_override(pid: u16) -> Generator {
+        GeneratorBuilder::new().pid(pid).build()
+    }
+
+    /// Overrides the auto-detected machine id, e.g. with an id derived from a
+    /// pod ordinal when hostnames are not unique.
+    pub fn with_machine_id(mut self, id: [u8; 3]) -> Generator {
+        self.machine_id = id;
+        self.machine_id_random = false;
+        self
+    }
+
+    /// The next id gets `value` (lower 24 bits) as counter, whatever was
+    /// generated before, e.g. to replay a sequence of ids in tests. Ids of the
+    /// same second can then repeat, never use it in production.
+    pub fn reset_counter(&self, value: u32) {
+        self.state.store(initial_state(value), Ordering::SeqCst);
+    }
+
+    /// Number of ids generated so far, random ones (`new_random_id`) included.
+    /// Unlike the counter of the ids it never wraps around (e.g. for metrics).
+    /// Starts at zero for clones.
+    pub fn ids_generated(&self) -> u64 {
+        self.generated.load(Ordering::Relaxed)
+    }
+
+    /// Machine id written in every id, e.g. to log which one was detected.
+    pub fn machine_id(&self) -> [u8; 3] {
+        self.machine_id
+    }
+
+    /// True when no machine id could be detected (nor was set) and a random
+    /// one is used instead: ids stay unique but can not be traced back to the
+    /// host, worth an alert at startup. `new_generator_checked()` fails in
+    /// that case instead.
+    pub fn machine_id_is_random(&self) -> bool {
+        self.machine_id_random
+    }
+
+    /// Pid written in every id, as returned by `ID::pid()`.
+    pub fn pid(&self) -> u16 {
+        self.pid.load(Ordering::SeqCst)
+    }
+
+    /// Forked processes (e.g. pre-fork servers) inherit the generator of their
+    /// parent and would generate the very same ids: call this in the child to
+    /// detect its pid again. It replaces any pid set explicitly.
+    #[cfg(feature = "std")]
+    pub fn refresh_pid(&self) {
+        self.pid.store(get_pid(), Ordering::SeqCst);
+    }
+
+    /// Overrides the pid field, the bytes are written verbatim at positions 7
+    /// and 8 of every id.
+    pub fn with_pid_bytes(mut self, pid: [u8; 2]) -> Generator {
+        self.pid = AtomicU16::new(BigEndian::read_u16(&pid));
+        self
+    }
+
+    /// Ids generated by `new_id` never go backward: when the system clock is
+    /// set back (e.g. by NTP), the latest timestamp is reused until the clock
+    /// catches up, so the embedded time may be slightly ahead of the wall
+    /// clock during that period. This also holds when ids with older explicit
+    /// times (`new_id_with_time`...) are generated in between, but those are
+    /// not ordered with the ids of `new_id`.
+    #[cfg(feature = "std")]
+    pub fn new_id(&self) -> Result<ID, Error> {
+        self.next_clock_id(self.now()?)
+    }
+
+    /// Same as `new_id`, but when the counter is within `threshold` of wrapping
+    /// around in the current second, sleeps until the next second instead of
+    /// carrying the id into it (see `reserve`), so that timestamps never run
+    /// ahead of the clock. This adds up to one second of latency per call
+    /// (more while the clock is set back), and is best effort: ids generated
+    /// concurrently with `new_id` can still be carried. Not available on
+    /// wasm32-unknown-unknown, where threads can not sleep.
+    #[cfg(all(
+        feature = "std",
+        not(all(target_arch = "wasm32", target_os = "unknown"))
+    ))]
+    pub fn new_id_throttled(&self, threshold: u32) -> Result<ID, Error> {
+        loop {
+            let now = self.now()?;
+            let current = self.state.load(Ordering::SeqCst);
+
+            let (last, carry, counter) = (
+                current >> 32,
+                current >> 24 & 0xFF,
+                current & (COUNTER_MAX - 1),
+            );
+
+            if carry == FRESH
+                || last < now & 0xFFFF_FFFF
+                || counter + u64::from(threshold) < COUNTER_MAX
+            {
+                return self.next_clock_id(now);
+            }
+
+            let elapsed = system_now().duration_since(UNIX_EPOCH)?;
+
+            std::thread::sleep(Duration::from_secs(1) - Duration::new(0, elapsed.subsec_nanos()));
+        }
+    }
+
+    /// Generates `n` ascending ids, reading the clock only once.
+    #[cfg(feature = "std")]
+    pub fn new_ids(&self, n: usize) -> Result<Vec<ID>, Error> {
+        let ts = self.now()?;
+
+        let mut ids = Vec::with_capacity(n);
+
+        for _ in 0..n {
+            ids.push(self.next_clock_id(ts)?);
+        }
+
+        Ok(ids)
+    }
+
+    /// Id with the current timestamp and 8 bytes from the OS random source
+    /// instead of the machine id, pid and counter: it is as unpredictable as
+    /// 64 random bits, but unicity is only probabilistic (ids of the same
+    /// second collide with a 2^-64 chance per pair instead of never). Such
+    /// ids still sort by time and use the same encoding, `machine()`, `pid()`
+    /// and `counter()` just return random values. Fails with `Error::Rng` if
+    /// the OS random source is not available.
+    #[cfg(feature = "std")]
+    pub fn new_random_id(&self) -> Result<ID, Error> {
+        let mut b = [0u8; ID_LEN];
+
+        BigEndian::write_u32(&mut b[..4], self.now()? as u32);
+
+        rand::rngs::OsRng::new()
+            .and_then(|mut rng| rng.try_fill_bytes(&mut b[4..]))
+            .map_err(|_| Error::Rng)?;
+
+        self.generated.fetch_add(1, Ordering::Relaxed);
+
+        Ok(ID::from_bytes(b))
+    }
+
+    /// Infinite stream of `new_id()` results.
+    #[cfg(feature = "std")]
+    pub fn iter_results(&self) -> impl Iterator<Item = Result<ID, Error>> + '_ {
+        std::iter::repeat_with(move || self.new_id())
+    }
+
+    /// Infinite stream of ids, e.g. `g.iter().take(100).collect()`. Panics if
+    /// an id can not be generated (system clock before the Unix epoch), use
+    /// `iter_results()` to handle that case.
+    #[cfg(feature = "std")]
+    pub fn iter(&self) -> impl Iterator<Item = ID> + '_ {
+        self.iter_results()
+            .map(|id| id.expect("can not generate id"))
+    }
+
+    // Current timestamp, never lower than the one previously returned.
+    #[cfg(feature = "std")]
+    fn now(&self) -> Result<u64, Error> {
+        match system_now().duration_since(UNIX_EPOCH) {
+            Ok(n) => {
+                let now = n.as_secs();
+
+                Ok(now.max(self.last_time.fetch_max(now, Ordering::SeqCst)))
+            },
+            Err(e) => Err(Error::SystemTime(e)),
+        }
     }
 
-    pub fn new_id_with_time(&self, t: SystemTime) -> Result<ID, IDGenerationError> {
+    /// Fails when `t` is before the Unix epoch. Once the 16,777,216 ids of the
+    /// second of `t` are used, the following ids are stamped with the next
+    /// seconds (see `reserve`).
+    #[cfg(feature = "std")]
+    pub fn new_id_with_time(&self, t: SystemTime) -> Result<ID, Error> {
         match t.duration_since(UNIX_EPOCH) {
-            Ok(n) => Ok(self.generate(n.as_secs())),
-            Err(e) => Err(IDGenerationError(e.to_string())),
+            Ok(n) => self.next_id(n.as_secs()),
+            Err(e) => Err(Error::SystemTime(e)),
+        }
+    }
+
+    /// Generates `n` ids stamped with `t`, with consecutive counters. When the
+    /// counter wraps around within the batch, the following ids are carried to
+    /// the next seconds like with `new_id_with_time`, so the ids are always
+    /// ascending.
+    #[cfg(feature = "std")]
+    pub fn new_ids_with_time(&self, n: usize, t: SystemTime) -> Result<Vec<ID>, Error> {
+        let ts = t.duration_since(UNIX_EPOCH)?.as_secs();
+
+        let mut ids = Vec::with_capacity(n);
+
+        for _ in 0..n {
+            ids.push(self.next_id(ts)?);
+        }
+
+        Ok(ids)
+    }
+
+    /// Same as `new_id_with_time` with a timestamp in seconds since the Unix
+    /// epoch, this is the way to generate ids without the `std` feature. There
+    /// is no time conversion which could fail, the only possible error is
+    /// `Error::CounterExhausted`.
+    pub fn new_id_with_timestamp(&self, secs: u32) -> Result<ID, Error> {
+        self.next_id(u64::from(secs))
+    }
+
+    fn next_id(&self, ts: u64) -> Result<ID, Error> {
+        self.next_id_until(ts, 0xFFFF_FFFF)
+    }
+
+    // Same as `next_id` for a timestamp read by `now`. An older explicit time
+    // resets the carried state of `reserve`, the timestamps carried for the
+    // clock are kept in `last_time` so that `now` never goes below them.
+    #[cfg(feature = "std")]
+    fn next_clock_id(&self, ts: u64) -> Result<ID, Error> {
+        let id = self.next_id(ts)?;
+
+        self.last_time
+            .fetch_max(u64::from(id.timestamp()), Ordering::SeqCst);
+
+        Ok(id)
+    }
+
+    // Same as `next_id`, but fails instead of stamping the id with a second
+    // after `limit`.
+    fn next_id_until(&self, ts: u64, limit: u64) -> Result<ID, Error> {
+        match self.reserve(ts, limit) {
+            Some((ts, counter)) => {
+                self.generated.fetch_add(1, Ordering::Relaxed);
+
+                Ok(ID::from_parts(ts, self.machine_id, self.pid(), counter))
+            },
+            None => Err(Error::CounterExhausted),
+        }
+    }
+
+    // Returns the timestamp and counter of the next id for the second `ts`.
+    //
+    // Counters of the ids sharing a timestamp are strictly increasing: when
+    // the 24 bits counter wraps around, the timestamp is carried one second
+    // ahead of `ts`, and kept there while the requested seconds are within the
+    // carried range. Returns `None` once `MAX_CARRY` seconds were borrowed, or
+    // when the id would be stamped after `limit`.
+    fn reserve(&self, ts: u64, limit: u64) -> Option<(u32, u32)> {
+        let ts = ts & 0xFFFF_FFFF;
+
+        let mut current = self.state.load(Ordering::SeqCst);
+
+        loop {
+            let (last, carry, counter) = (
+                current >> 32,
+                current >> 24 & 0xFF,
+                current & (COUNTER_MAX - 1),
+            );
+            let next = (counter + 1) & (COUNTER_MAX - 1);
+
+            let (time, carry, counter) = if carry == FRESH {
+                (ts, 0, counter)
+            } else if ts > last || ts + carry < last {
+                (ts, 0, next)
+            } else if next != 0 {
+                (last, carry, next)
+            } else if carry < MAX_CARRY && last < 0xFFFF_FFFF {
+                (last + 1, carry + 1, next)
+            } else {
+                return None;
+            };
+
+            if time > limit {
+                return None;
+            }
+
+            match self.state.compare_exchange_weak(
+                current,
+                time << 32 | carry << 24 | counter,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            ) {
+                Ok(_) => return Some((time as u32, counter as u32)),
+                Err(x) => current = x,
+            }
         }
     }
 
-    fn generate(&self, ts: u64) -> ID {
-        let mut buff = [0u8; ID_LEN];
+    /// Generates `count` ids whose timestamps are evenly spread between `start`
+    /// and `end` (both truncated to the second). The returned ids are sorted.
+    /// Fails with `Error::CounterExhausted` rather than carrying an id past
+    /// `end` (see `reserve`) once the counter wrapped around in its second.
+    #[cfg(feature = "std")]
+    pub fn generate_spanning(
+        &self,
+        start: SystemTime,
+        end: SystemTime,
+        count: usize,
+    ) -> Result<Vec<ID>, Error> {
+        let from = match start.duration_since(UNIX_EPOCH) {
+            Ok(n) => n.as_secs(),
+            Err(e) => return Err(Error::SystemTime(e)),
+        };
+
+        let to = match end.duration_since(UNIX_EPOCH) {
+            Ok(n) => n.as_secs(),
+            Err(e) => return Err(Error::SystemTime(e)),
+        };
+
+        if to < from {
+            return Err(Error::InvalidRange);
+        }
+
+        let span = u128::from(to - from);
+        let steps = count.saturating_sub(1).max(1) as u128;
 
-        BigEndian::write_u32(&mut buff, ts as u32);
+        let mut ids = (0..count)
+            .map(|i| self.next_id_until(from + (span * i as u128 / steps) as u64, to))
+            .collect::<Result<Vec<ID>, Error>>()?;
 
-        buff[4] = self.machine_id[0];
-        buff[5] = self.machine_id[1];
-        buff[6] = self.machine_id[2];
+        // ids generated concurrently by other threads may be interleaved
+        ids.sort();
 
-        buff[7] = (self.pid >> 8) as u8;
-        buff[8] = self.pid as u8;
+        Ok(ids)
+    }
 
-        let i = self.counter.fetch_add(1, Ordering::SeqCst);
-        buff[9] = (i >> 16) as u8;
-        buff[10] = (i >> 8) as u8;
-        buff[11] = (i) as u8;
+    // Counter of the next generated id, when it is not carried to the next
+    // second.
+    fn next_counter(&self) -> u32 {
+        let state = self.state.load(Ordering::SeqCst);
 
-        ID { val: buff }
+        if state >> 24 & 0xFF == FRESH {
+            (state & (COUNTER_MAX - 1)) as u32
+        } else {
+            ((state + 1) & (COUNTER_MAX - 1)) as u32
+        }
     }
 }
 
@@ -182,25 +979,42 @@ impl fmt::Debug for Generator {
         write!(
             f,
             "Generator {{counter: {:?}, machine_id: {:?}, pid: {:?}}}",
-            self.counter, self.machine_id, self.pid
+            self.next_counter(),
+            self.machine_id,
+            self.pid()
         )
     }
 }
 
 // ---
 
-#[derive(Clone, Hash)]
+/// A 12 bytes globally unique id, sorting by creation time.
+#[derive(Clone, Copy, Hash, PartialEq, Eq)]
 pub struct ID {
     val: [u8; ID_LEN],
 }
 
 impl ID {
+    /// The all zero id, sorts before any other id.
+    pub const fn nil() -> ID {
+        ID { val: [0u8; ID_LEN] }
+    }
+
+    /// True for the all zero id, see `nil()`.
+    pub fn is_nil(&self) -> bool {
+        self.val == [0u8; ID_LEN]
+    }
+
+    /// Canonical 20 chars lowercase base32 form of the id.
     pub fn encode(&self) -> String {
-        let alphabet = String::from("0123456789abcdefghijklmnopqrstuv");
+        self.encode_str(&mut [0u8; 20]).to_string()
+    }
 
-        let buff = alphabet.as_bytes();
+    /// Writes the 20 chars of `encode()` into `out`, without allocating.
+    pub fn encode_into(&self, out: &mut [u8; 20]) {
+        let buff = ENCODING;
 
-        std::str::from_utf8(&[
+        *out = [
             buff[(self.val[0] as usize) >> 3],
             buff[(self.val[1] as usize) >> 6 & 0x1F | ((self.val[0] as usize) << 2) & 0x1F],
             buff[((self.val[1] as usize) >> 1) & 0x1F],
@@ -221,58 +1035,54 @@ impl ID {
             buff[((self.val[11] as usize) >> 6) & 0x1F | ((self.val[10] as usize) << 2) & 0x1F],
             buff[((self.val[11] as usize) >> 1) & 0x1F],
             buff[((self.val[11] as usize) << 4) & 0x1F],
-        ])
-        .unwrap()
-        .to_string()
-    }
-
-    pub fn decode(input: &str) -> Self {
-        let mut dec = [1u8; 256];
-
-        dec[48] = 0 as u8;
-        dec[49] = 1 as u8;
-        dec[50] = 2 as u8;
-        dec[51] = 3 as u8;
-        dec[52] = 4 as u8;
-        dec[53] = 5 as u8;
-        dec[54] = 6 as u8;
-        dec[55] = 7 as u8;
-        dec[56] = 8 as u8;
-        dec[57] = 9 as u8;
-        dec[97] = 10 as u8;
-        dec[98] = 11 as u8;
-        dec[99] = 12 as u8;
-        dec[100] = 13 as u8;
-        dec[101] = 14 as u8;
-        dec[102] = 15 as u8;
-        dec[103] = 16 as u8;
-        dec[104] = 17 as u8;
-        dec[105] = 18 as u8;
-        dec[106] = 19 as u8;
-        dec[107] = 20 as u8;
-        dec[108] = 21 as u8;
-        dec[109] = 22 as u8;
-        dec[110] = 23 as u8;
-        dec[111] = 24 as u8;
-        dec[112] = 25 as u8;
-        dec[113] = 26 as u8;
-        dec[114] = 27 as u8;
-        dec[115] = 28 as u8;
-        dec[116] = 29 as u8;
-        dec[117] = 30 as u8;
-        dec[118] = 31 as u8;
-
-        // XXX: the code commented below generated the array above
-        // let alphabet = String::from("0123456789abcdefghijklmnopqrstuv");
-        // let buff = alphabet.as_bytes();
-        //
-        // for i in 0..alphabet2.len() {
-        //     dec[alphabet2[i] as usize] = i as u8
-        // }
+        ];
+    }
+
+    /// Writes the 20 chars of `encode()` into `w`, without allocating.
+    #[cfg(feature = "std")]
+    pub fn encode_to_writer<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let mut buff = [0u8; 20];
+        self.encode_into(&mut buff);
+
+        w.write_all(&buff)
+    }
+
+    /// Same as `encode_into()`, returning the written chars as a `&str`.
+    pub fn encode_str<'a>(&self, out: &'a mut [u8; 20]) -> &'a str {
+        self.encode_into(out);
+
+        // only chars of ENCODING have been written
+        core::str::from_utf8(out).unwrap()
+    }
+
+    /// Uppercase variant of `encode()`, for systems comparing ids case
+    /// insensitively. It is not canonical: only `decode_with` accepts it (see
+    /// `DecodeOptions::uppercase`) and, as a string, it does not sort like the
+    /// ids, only the lowercase form does.
+    pub fn encode_upper(&self) -> String {
+        self.encode().to_ascii_uppercase()
+    }
+
+    /// Returns the first 8 chars of `encode()`, for display purposes only (UI
+    /// badges, logs...). It can not be decoded back and collides far more
+    /// often than the full id, never use it as an identifier.
+    pub fn short(&self) -> String {
+        let mut encoded = self.encode();
+        encoded.truncate(8);
+        encoded
+    }
+
+    /// Decodes the canonical form produced by `encode()`: exactly 20 lowercase
+    /// chars, without padding. Use `decode_with` to accept uppercase or padded
+    /// input.
+    pub fn decode(input: &str) -> Result<Self, DecodeError> {
+        validate(input)?;
+
+        let dec = &DECODING;
 
         let src = input.as_bytes();
 
-        ID {
+        Ok(ID {
             val: [
                 dec[src[0] as usize] << 3 | dec[src[1] as usize] >> 2,
                 dec[src[1] as usize] << 6 | dec[src[2] as usize] << 1 | dec[src[3] as usize] >> 4,
@@ -291,433 +1101,3600 @@ impl ID {
                     | dec[src[18] as usize] << 1
                     | dec[src[19] as usize] >> 4,
             ],
-        }
+        })
     }
 
-    pub fn machine(&self) -> [u8; 3] {
-        [self.val[4], self.val[5], self.val[6]]
-    }
+    /// Decodes with the given strictness. `DecodeOptions::default()` only
+    /// accepts the canonical form (`encode()`), like `decode`.
+    pub fn decode_with(input: &str, opts: DecodeOptions) -> Result<Self, DecodeError> {
+        let input = if opts.padding {
+            input.strip_suffix("====").unwrap_or(input)
+        } else {
+            input
+        };
+
+        if opts.uppercase && input.len() == 20 {
+            // lowercasing ASCII chars keeps the input valid UTF-8
+            let mut lower = [0u8; 20];
+            lower.copy_from_slice(input.as_bytes());
+            lower.make_ascii_lowercase();
+
+            return ID::decode(core::str::from_utf8(&lower).unwrap()).map_err(|e| match e {
+                // report the char as given
+                DecodeError::InvalidCharacter { position, .. } => {
+                    DecodeError::invalid_character(input, position)
+                },
+                e => e,
+            });
+        }
 
-    pub fn pid(&self) -> u16 {
-        BigEndian::read_u16(&[self.val[7], self.val[8]])
+        ID::decode(input)
     }
 
-    pub fn time(&self) -> SystemTime {
-        let ts = BigEndian::read_u32(&[self.val[0], self.val[1], self.val[2], self.val[3]]);
+    /// Packs the components exactly like a generator does, only the lower 24
+    /// bits of `counter` are stored.
+    pub fn from_parts(timestamp: u32, machine: [u8; 3], pid: u16, counter: u32) -> ID {
+        let mut val = [0u8; ID_LEN];
 
-        UNIX_EPOCH + Duration::from_secs(u64::from(ts))
-    }
+        BigEndian::write_u32(&mut val, timestamp);
 
-    pub fn counter(&self) -> u32 {
-        u32::from(self.val[9]) << 16 | u32::from(self.val[10]) << 8 | (u32::from(self.val[11]))
-    }
-}
+        val[4] = machine[0];
+        val[5] = machine[1];
+        val[6] = machine[2];
 
-impl fmt::Debug for ID {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "ID: {:?}", self.val)
-    }
-}
+        val[7] = (pid >> 8) as u8;
+        val[8] = pid as u8;
 
-impl fmt::Display for ID {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "ID: {:?}", self.encode())
+        val[9] = (counter >> 16) as u8;
+        val[10] = (counter >> 8) as u8;
+        val[11] = counter as u8;
+
+        ID { val }
     }
-}
 
-impl PartialEq for ID {
-    fn eq(&self, other: &ID) -> bool {
-        self.val == other.val
+    /// Id from its raw binary representation, see `as_bytes`.
+    pub fn from_bytes(b: [u8; ID_LEN]) -> ID {
+        ID { val: b }
     }
-}
 
-impl From<&str> for ID {
-    // TODO: implement try_from https://doc.rust-lang.org/std/convert/trait.TryFrom.html when no
-    // longer nightly
-    fn from(s: &str) -> Self {
-        if s.len() == 20 {
-            return ID::decode(s);
+    /// Same as `from_bytes` for untrusted input: rejects ids whose embedded
+    /// time is not between `min_time` and `max_time` (inclusive, truncated to
+    /// the second), which usually means the bytes are corrupted. Bounds past
+    /// 2106 accept every id up to the last 32 bits second.
+    #[cfg(feature = "std")]
+    pub fn from_bytes_validated(
+        b: [u8; ID_LEN],
+        min_time: SystemTime,
+        max_time: SystemTime,
+    ) -> Result<ID, DecodeError> {
+        let id = ID::from_bytes(b);
+
+        if id < ID::min_for_time(min_time) || id > ID::max_for_time(max_time) {
+            return Err(DecodeError::TimestampOutOfRange);
         }
 
-        ID { val: [0u8; ID_LEN] }
+        Ok(id)
     }
-}
 
-impl Eq for ID {}
-
-impl PartialOrd for ID {
-    fn partial_cmp(&self, other: &ID) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
+    /// Raw binary representation, compatible with Mongo Object IDs.
+    pub fn as_bytes(&self) -> &[u8; ID_LEN] {
+        &self.val
     }
-}
 
-impl Ord for ID {
-    fn cmp(&self, other: &ID) -> std::cmp::Ordering {
-        self.val.cmp(&other.val)
+    /// Same as `as_bytes`, by value.
+    pub fn into_bytes(self) -> [u8; ID_LEN] {
+        self.val
     }
-}
 
-impl Serialize for ID {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: Serializer,
-    {
-        serializer.serialize_str(self.encode().as_str())
+    /// Key for byte-wise ordered stores (RocksDB, LMDB...): comparing keys
+    /// lexicographically always gives the same order as comparing the ids.
+    pub fn sortable_key(&self) -> [u8; ID_LEN] {
+        self.val
     }
-}
 
-struct IDVisitor;
-
-impl<'de> Visitor<'de> for IDVisitor {
-    type Value = ID;
+    /// Mongo Object IDs have the same layout: a big-endian 4 bytes timestamp
+    /// followed by 5 bytes of process unique value (machine id and pid here)
+    /// and a big-endian 3 bytes counter, so the bytes are taken verbatim.
+    pub fn from_object_id(bytes: [u8; ID_LEN]) -> ID {
+        ID { val: bytes }
+    }
 
-    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        formatter.write_str("a str")
+    /// Bytes of the equivalent Mongo Object ID, see `from_object_id`.
+    pub fn to_object_id(&self) -> [u8; ID_LEN] {
+        self.val
     }
 
-    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
-    where
-        E: de::Error,
-    {
-        Ok(ID::from(value))
+    /// 24 chars lowercase hexadecimal form of the 12 bytes, as used by Mongo for
+    /// Object IDs.
+    pub fn to_mongo_object_id_hex(&self) -> String {
+        format!("{:x}", self)
     }
-}
 
-impl<'de> Deserialize<'de> for ID {
+    /// Parses the 24 chars hexadecimal form of a Mongo Object ID, both lower and
+    /// upper case digits are accepted.
+    pub fn from_mongo_object_id_hex(input: &str) -> Result<ID, DecodeError> {
+        let src = input.as_bytes();
+
+        if src.len() != ID_LEN * 2 {
+            return Err(DecodeError::InvalidLength {
+                expected: ID_LEN * 2,
+                actual: src.len(),
+            });
+        }
+
+        let mut val = [0u8; ID_LEN];
+
+        for (i, c) in src.iter().enumerate() {
+            let digit = match (*c as char).to_digit(16) {
+                Some(x) => x as u8,
+                None => return Err(DecodeError::invalid_character(input, i)),
+            };
+
+            val[i / 2] = val[i / 2] << 4 | digit;
+        }
+
+        Ok(ID { val })
+    }
+
+    /// The 12 bytes read as a big-endian integer, stored in the lower 96 bits
+    /// (the upper 32 bits are always zero). Ordering is preserved.
+    pub fn to_u128(&self) -> u128 {
+        self.val
+            .iter()
+            .fold(0u128, |acc, b| acc << 8 | u128::from(*b))
+    }
+
+    /// Inverse of `to_u128`, the upper 32 bits of `v` are ignored.
+    pub fn from_u128(v: u128) -> ID {
+        let mut buff = [0u8; 16];
+        BigEndian::write_u128(&mut buff, v);
+
+        let mut val = [0u8; ID_LEN];
+        val.copy_from_slice(&buff[16 - ID_LEN..]);
+
+        ID { val }
+    }
+
+    /// Reads exactly 12 raw bytes from `r`, fails with
+    /// `io::ErrorKind::UnexpectedEof` when the stream ends before.
+    #[cfg(feature = "std")]
+    pub fn from_reader<R: Read>(r: &mut R) -> io::Result<ID> {
+        let mut val = [0u8; ID_LEN];
+
+        r.read_exact(&mut val)?;
+
+        Ok(ID { val })
+    }
+
+    /// Writes the 12 raw bytes of the id (`as_bytes()`) into `w`, e.g. for
+    /// append-only logs of ids. There is no framing: ids are read back one
+    /// after the other with `read_packed`.
+    #[cfg(feature = "std")]
+    pub fn write_packed<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        w.write_all(&self.val)
+    }
+
+    /// Reads an id written by `write_packed`, same as `from_reader`.
+    #[cfg(feature = "std")]
+    pub fn read_packed<R: Read>(r: &mut R) -> io::Result<ID> {
+        ID::from_reader(r)
+    }
+
+    /// Stores the id in the first 12 bytes of a `Uuid`, the last 4 bytes are
+    /// zero. The result is not a valid RFC 4122 UUID (no version nor variant
+    /// bits) but keeps the ordering of the ids, and `from_uuid` gives the id
+    /// back.
+    #[cfg(feature = "uuid")]
+    pub fn to_uuid(&self) -> uuid::Uuid {
+        let mut b = [0u8; 16];
+        b[..ID_LEN].copy_from_slice(&self.val);
+
+        uuid::Uuid::from_bytes(b)
+    }
+
+    /// Reverse of `to_uuid`, `None` if the padding bytes are not zero (the
+    /// `Uuid` was not made from an id).
+    #[cfg(feature = "uuid")]
+    pub fn from_uuid(u: uuid::Uuid) -> Option<ID> {
+        let b = u.as_bytes();
+
+        if b[ID_LEN..] != [0; 16 - ID_LEN] {
+            return None;
+        }
+
+        let mut val = [0u8; ID_LEN];
+        val.copy_from_slice(&b[..ID_LEN]);
+
+        Some(ID { val })
+    }
+
+    /// Machine id of the generator of the id.
+    pub fn machine(&self) -> [u8; 3] {
+        [self.val[4], self.val[5], self.val[6]]
+    }
+
+    /// Pid of the generator of the id, see `pid_bytes`.
+    pub fn pid(&self) -> u16 {
+        BigEndian::read_u16(&[self.val[7], self.val[8]])
+    }
+
+    /// Raw bytes of the pid field, `pid()` is their big-endian value.
+    pub fn pid_bytes(&self) -> [u8; 2] {
+        [self.val[7], self.val[8]]
+    }
+
+    /// True when both ids were generated during the same second.
+    pub fn within_second(&self, other: &ID) -> bool {
+        self.val[..4] == other.val[..4]
+    }
+
+    /// Grouping key of the ids generated during the same second, the same as
+    /// `timestamp()`.
+    pub fn second_bucket(&self) -> u32 {
+        self.timestamp()
+    }
+
+    /// True when both ids have the same machine id and pid, i.e. they were
+    /// most likely generated by the same process.
+    pub fn same_source(&self, other: &ID) -> bool {
+        self.val[4..9] == other.val[4..9]
+    }
+
+    /// Seconds since the Unix epoch, as stored in the first 4 bytes.
+    pub fn timestamp(&self) -> u32 {
+        BigEndian::read_u32(&[self.val[0], self.val[1], self.val[2], self.val[3]])
+    }
+
+    /// Creation time of the id, truncated to the second.
+    #[cfg(feature = "std")]
+    pub fn time(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(u64::from(self.timestamp()))
+    }
+
+    /// Smallest and greatest ids of the second of `t`, as bounds for range
+    /// queries (e.g. `map.range(ID::min_for_time(a)..=ID::max_for_time(b))`).
+    /// Times before the Unix epoch map to the epoch, times after the last
+    /// second of the 32 bits timestamp (in 2106) map to that second.
+    #[cfg(feature = "std")]
+    pub fn min_for_time(t: SystemTime) -> ID {
+        ID::bound_for_time(t, 0)
+    }
+
+    /// All the bytes after the timestamp are 0xFF, no id of that second is
+    /// greater.
+    #[cfg(feature = "std")]
+    pub fn max_for_time(t: SystemTime) -> ID {
+        ID::bound_for_time(t, 0xFF)
+    }
+
+    #[cfg(feature = "std")]
+    fn bound_for_time(t: SystemTime, fill: u8) -> ID {
+        let secs = t.duration_since(UNIX_EPOCH).map_or(0, |n| n.as_secs());
+
+        let mut val = [fill; ID_LEN];
+        BigEndian::write_u32(&mut val, secs.min(u64::from(u32::MAX)) as u32);
+
+        ID { val }
+    }
+
+    /// How long ago the id was generated. Fails when the embedded timestamp is
+    /// in the future (e.g. clock skew between hosts).
+    #[cfg(feature = "std")]
+    pub fn age(&self) -> Result<Duration, SystemTimeError> {
+        self.age_since(system_now())
+    }
+
+    /// Same as `age`, relative to `now` instead of the system clock.
+    #[cfg(feature = "std")]
+    pub fn age_since(&self, now: SystemTime) -> Result<Duration, SystemTimeError> {
+        now.duration_since(self.time())
+    }
+
+    /// Counter of the generator of the id, 24 bits wide.
+    pub fn counter(&self) -> u32 {
+        u32::from(self.val[9]) << 16 | u32::from(self.val[10]) << 8 | (u32::from(self.val[11]))
+    }
+}
+
+/// Time partition of `ID::storage_key`: one prefix per day (`2019/06/15`) or
+/// per hour (`2019/06/15/13`).
+#[cfg(feature = "chrono")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Granularity {
+    Day,
+    Hour,
+}
+
+#[cfg(feature = "chrono")]
+impl ID {
+    /// Returns an object storage key prefixed by the id creation date (UTC),
+    /// e.g. `2019/06/15/9m4e2mr0ui3e8a215n4g`, so that keys are partitioned by
+    /// time.
+    pub fn storage_key(&self, granularity: Granularity) -> String {
+        let t = chrono::DateTime::<chrono::Utc>::from(self.time());
+
+        let prefix = match granularity {
+            Granularity::Day => t.format("%Y/%m/%d"),
+            Granularity::Hour => t.format("%Y/%m/%d/%H"),
+        };
+
+        format!("{}/{}", prefix, self.encode())
+    }
+}
+
+/// By default only the encoded form and the timestamp are shown so that logs do
+/// not spell out machine ids and pids, use `{:#?}` to get every component.
+/// Note that this is not a security boundary: the encoded form can still be
+/// decoded.
+impl fmt::Debug for ID {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let alternate = f.alternate();
+
+        let mut d = f.debug_struct("ID");
+
+        d.field("encoded", &self.encode())
+            .field("time", &self.timestamp());
+
+        if alternate {
+            d.field("machine", &self.machine())
+                .field("pid", &self.pid())
+                .field("counter", &self.counter());
+        }
+
+        d.finish()
+    }
+}
+
+impl fmt::Display for ID {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.encode_str(&mut [0u8; 20]))
+    }
+}
+
+/// Hexadecimal form of the 12 bytes, `{:x}` gives `to_mongo_object_id_hex()`.
+impl fmt::LowerHex for ID {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.val.iter().try_for_each(|b| write!(f, "{:02x}", b))
+    }
+}
+
+impl fmt::UpperHex for ID {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.val.iter().try_for_each(|b| write!(f, "{:02X}", b))
+    }
+}
+
+impl AsRef<[u8]> for ID {
+    fn as_ref(&self) -> &[u8] {
+        &self.val
+    }
+}
+
+/// `Eq`, `Ord` and `Hash` of an ID are the ones of its bytes, so maps and sets
+/// of ids can be queried with raw bytes, e.g. `set.contains(&[0u8; 12])`.
+impl Borrow<[u8; ID_LEN]> for ID {
+    fn borrow(&self) -> &[u8; ID_LEN] {
+        &self.val
+    }
+}
+
+impl From<[u8; ID_LEN]> for ID {
+    fn from(b: [u8; ID_LEN]) -> Self {
+        ID::from_bytes(b)
+    }
+}
+
+impl TryFrom<&[u8]> for ID {
+    type Error = DecodeError;
+
+    fn try_from(b: &[u8]) -> Result<Self, Self::Error> {
+        if b.len() != ID_LEN {
+            return Err(DecodeError::InvalidLength {
+                expected: ID_LEN,
+                actual: b.len(),
+            });
+        }
+
+        let mut val = [0u8; ID_LEN];
+        val.copy_from_slice(b);
+
+        Ok(ID { val })
+    }
+}
+
+impl TryFrom<&str> for ID {
+    type Error = DecodeError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        ID::decode(s)
+    }
+}
+
+impl TryFrom<String> for ID {
+    type Error = DecodeError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        ID::decode(&s)
+    }
+}
+
+impl From<ID> for String {
+    fn from(id: ID) -> String {
+        id.encode()
+    }
+}
+
+impl Default for ID {
+    fn default() -> Self {
+        ID::nil()
+    }
+}
+
+impl FromStr for ID {
+    type Err = DecodeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ID::decode(s)
+    }
+}
+
+impl PartialOrd for ID {
+    fn partial_cmp(&self, other: &ID) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ID {
+    fn cmp(&self, other: &ID) -> core::cmp::Ordering {
+        self.val.cmp(&other.val)
+    }
+}
+
+impl PartialEq<str> for ID {
+    fn eq(&self, other: &str) -> bool {
+        self.partial_cmp(other) == Some(core::cmp::Ordering::Equal)
+    }
+}
+
+impl PartialEq<String> for ID {
+    fn eq(&self, other: &String) -> bool {
+        self == other.as_str()
+    }
+}
+
+impl<'a> PartialEq<&'a str> for ID {
+    fn eq(&self, other: &&'a str) -> bool {
+        self == *other
+    }
+}
+
+impl PartialEq<ID> for str {
+    fn eq(&self, other: &ID) -> bool {
+        other == self
+    }
+}
+
+impl PartialEq<ID> for &str {
+    fn eq(&self, other: &ID) -> bool {
+        other == *self
+    }
+}
+
+impl PartialEq<ID> for String {
+    fn eq(&self, other: &ID) -> bool {
+        other == self.as_str()
+    }
+}
+
+/// Comparing an ID with a string decodes the string and compares the raw bytes,
+/// so the result always agrees with `Ord`. A malformed string is never equal to
+/// an ID and can not be ordered against it (`partial_cmp` returns `None`).
+/// Every other comparison between ids and strings, `==` included, goes through
+/// this one.
+impl PartialOrd<str> for ID {
+    fn partial_cmp(&self, other: &str) -> Option<core::cmp::Ordering> {
+        ID::decode(other).ok().map(|id| self.cmp(&id))
+    }
+}
+
+impl PartialOrd<String> for ID {
+    fn partial_cmp(&self, other: &String) -> Option<core::cmp::Ordering> {
+        self.partial_cmp(other.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for ID {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(self.encode_str(&mut [0u8; 20]))
+        } else {
+            serializer.serialize_bytes(&self.val)
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+struct IDVisitor;
+
+#[cfg(feature = "serde")]
+impl<'de> Visitor<'de> for IDVisitor {
+    type Value = ID;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a 20 chars base32 encoded id or its 12 raw bytes")
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        ID::decode(value).map_err(de::Error::custom)
+    }
+
+    // 20 bytes are the string form, as written by binary formats before ids
+    // were serialized as raw bytes there.
+    fn visit_bytes<E>(self, value: &[u8]) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        match value.len() {
+            20 => match core::str::from_utf8(value) {
+                Ok(s) => self.visit_str(s),
+                Err(_) => Err(de::Error::invalid_value(
+                    de::Unexpected::Bytes(value),
+                    &self,
+                )),
+            },
+            _ => ID::try_from(value).map_err(de::Error::custom),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for ID {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>,
     {
-        deserializer.deserialize_str(IDVisitor)
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_str(IDVisitor)
+        } else {
+            deserializer.deserialize_bytes(IDVisitor)
+        }
+    }
+}
+
+// ---
+
+/// Checks that `input` is a well formed id, without decoding it: `ID::decode`
+/// fails if and only if this function fails.
+pub fn validate(input: &str) -> Result<(), DecodeError> {
+    let src = input.as_bytes();
+
+    if src.len() != 20 {
+        return Err(DecodeError::InvalidLength {
+            expected: 20,
+            actual: src.len(),
+        });
+    }
+
+    // indexed loop rather than iterator adapters, which are noticeably slower
+    // in unoptimized builds
+    let mut i = 0;
+    while i < src.len() {
+        if DECODING[src[i] as usize] == 0xFF {
+            return Err(DecodeError::invalid_character(input, i));
+        }
+        i += 1;
+    }
+
+    // the last char only carries 1 bit of the id
+    if DECODING[src[19] as usize] & 0x0F != 0 {
+        return Err(DecodeError::NonCanonical);
+    }
+
+    Ok(())
+}
+
+/// Same as `validate(input).is_ok()`.
+pub fn is_valid(input: &str) -> bool {
+    validate(input).is_ok()
+}
+
+/// Extracts the timestamp of an encoded id (same as `ID::timestamp()`) from its
+/// first 7 chars, without decoding the whole id. Only the length and these
+/// chars are checked, use `validate` to check the rest of the input.
+pub fn parse_timestamp(input: &str) -> Result<u32, DecodeError> {
+    let src = input.as_bytes();
+
+    if src.len() != 20 {
+        return Err(DecodeError::InvalidLength {
+            expected: 20,
+            actual: src.len(),
+        });
+    }
+
+    let mut bits = 0u64;
+
+    // 7 chars carry 35 bits, the first 32 are the timestamp
+    for (i, c) in src[..7].iter().enumerate() {
+        match DECODING[*c as usize] {
+            0xFF => return Err(DecodeError::invalid_character(input, i)),
+            x => bits = bits << 5 | u64::from(x),
+        }
+    }
+
+    Ok((bits >> 3) as u32)
+}
+
+/// Converts a Mongo Object ID in its 24 chars hex form to the xid string of the
+/// same 12 bytes, e.g. for migrations. Same as
+/// `ID::from_mongo_object_id_hex(hex)?.encode()`.
+pub fn from_mongo_hex_to_xid_string(hex: &str) -> Result<String, DecodeError> {
+    ID::from_mongo_object_id_hex(hex).map(|id| id.encode())
+}
+
+/// Strictness of `ID::decode_with`, everything is rejected by default but the
+/// canonical 20 lowercase chars, like `ID::decode` does.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DecodeOptions {
+    uppercase: bool,
+    padding: bool,
+}
+
+impl DecodeOptions {
+    /// Same as `DecodeOptions::default()`, only the canonical form is accepted.
+    pub fn new() -> DecodeOptions {
+        DecodeOptions::default()
+    }
+
+    /// Accepts uppercase (and mixed case) chars, decoded like their lowercase
+    /// counterparts.
+    pub fn uppercase(mut self, allow: bool) -> DecodeOptions {
+        self.uppercase = allow;
+        self
+    }
+
+    /// Accepts the `====` padding added by generic base32 encoders (which pad
+    /// the 20 chars to 24), the unpadded form is still accepted.
+    pub fn padding(mut self, allow: bool) -> DecodeOptions {
+        self.padding = allow;
+        self
+    }
+}
+
+// ---
+
+/// A list of ids stored as a single comma separated string, e.g. in a text
+/// column or a query parameter.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct IdList(pub Vec<ID>);
+
+impl IdList {
+    /// Comma separated encodings of the ids, also given by `Display`.
+    pub fn encode(&self) -> String {
+        self.0
+            .iter()
+            .map(ID::encode)
+            .collect::<Vec<String>>()
+            .join(",")
+    }
+}
+
+impl fmt::Display for IdList {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.encode())
+    }
+}
+
+impl FromStr for IdList {
+    type Err = DecodeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Ok(IdList(Vec::new()));
+        }
+
+        s.split(',')
+            .map(ID::decode)
+            .collect::<Result<Vec<ID>, DecodeError>>()
+            .map(IdList)
     }
 }
 
-// ---
+impl TryFrom<&str> for IdList {
+    type Error = DecodeError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for IdList {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.encode().as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+struct IdListVisitor;
+
+#[cfg(feature = "serde")]
+impl<'de> Visitor<'de> for IdListVisitor {
+    type Value = IdList;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a comma separated list of ids")
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        value.parse().map_err(de::Error::custom)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for IdList {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(IdListVisitor)
+    }
+}
+
+// ---
+
+/// Packed form of a list of ids: their encodings concatenated without any
+/// separator, every id being exactly 20 chars.
+pub fn encode_many(ids: &[ID]) -> String {
+    let mut out = String::with_capacity(ids.len() * 20);
+
+    for id in ids {
+        out.push_str(id.encode_str(&mut [0u8; 20]));
+    }
+
+    out
+}
+
+/// Parses the output of `encode_many`, the positions of invalid characters are
+/// relative to the whole input.
+pub fn decode_many(input: &str) -> Result<Vec<ID>, DecodeError> {
+    let src = input.as_bytes();
+
+    if !src.len().is_multiple_of(20) {
+        return Err(DecodeError::InvalidLength {
+            expected: src.len() - src.len() % 20 + 20,
+            actual: src.len(),
+        });
+    }
+
+    src.chunks(20)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let offset = i * 20;
+
+            let encoded = core::str::from_utf8(chunk)
+                .map_err(|e| DecodeError::invalid_character(input, offset + e.valid_up_to()))?;
+
+            ID::decode(encoded).map_err(|e| match e {
+                DecodeError::InvalidCharacter {
+                    position,
+                    character,
+                } => DecodeError::InvalidCharacter {
+                    position: offset + position,
+                    character,
+                },
+                e => e,
+            })
+        })
+        .collect()
+}
+
+// ---
+
+/// Summary of a set of ids, returned by `analyze`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Analysis {
+    /// number of distinct machine ids
+    pub machines: usize,
+    /// number of distinct pids
+    pub pids: usize,
+    /// highest number of ids sharing the same second, compare it with 2^24 to
+    /// check how close a host got to exhausting the counter
+    pub peak_per_second: u32,
+    /// the (earliest) second, since the Unix epoch, where the peak was reached
+    pub hottest_second: u64,
+}
+
+/// Counts the distinct machine ids and pids of `ids` and finds their busiest
+/// second, e.g. to check how close a workload gets to the 16,777,216 ids per
+/// second limit.
+pub fn analyze(ids: &[ID]) -> Analysis {
+    let mut machines = BTreeSet::new();
+    let mut pids = BTreeSet::new();
+    let mut per_second: BTreeMap<u64, u32> = BTreeMap::new();
+
+    for id in ids {
+        machines.insert(id.machine());
+        pids.insert(id.pid());
+
+        *per_second.entry(u64::from(id.timestamp())).or_insert(0) += 1;
+    }
+
+    let (hottest_second, peak_per_second) = per_second
+        .into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)))
+        .unwrap_or((0, 0));
+
+    Analysis {
+        machines: machines.len(),
+        pids: pids.len(),
+        peak_per_second,
+        hottest_second,
+    }
+}
+
+// ---
+
+/// k-way merge of already sorted streams (e.g. per shard exports) into a single
+/// sorted vector, duplicated ids are only kept once when `dedup` is set.
+pub fn merge_sorted(streams: Vec<Vec<ID>>, dedup: bool) -> Vec<ID> {
+    let mut out = Vec::with_capacity(streams.iter().map(Vec::len).sum());
+
+    let mut iters: Vec<alloc::vec::IntoIter<ID>> =
+        streams.into_iter().map(Vec::into_iter).collect();
+
+    let mut heap = BinaryHeap::with_capacity(iters.len());
+
+    for (i, it) in iters.iter_mut().enumerate() {
+        if let Some(id) = it.next() {
+            heap.push(Reverse((id, i)));
+        }
+    }
+
+    while let Some(Reverse((id, i))) = heap.pop() {
+        if let Some(next) = iters[i].next() {
+            heap.push(Reverse((next, i)));
+        }
+
+        if dedup && out.last() == Some(&id) {
+            continue;
+        }
+
+        out.push(id);
+    }
+
+    out
+}
+
+// ---
+
+#[cfg(feature = "std")]
+fn rand_int<R: RngCore>(rng: &mut R) -> u32 {
+    let mut buff = [0u8; 3];
+
+    rng.fill_bytes(&mut buff);
+
+    u32::from(buff[0]) << 16 | u32::from(buff[1]) << 8 | u32::from(buff[2])
+}
+
+#[cfg(all(
+    feature = "std",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+fn system_now() -> SystemTime {
+    SystemTime::now()
+}
+
+// `SystemTime::now()` panics on wasm32-unknown-unknown, the time comes from
+// javascript instead.
+#[cfg(all(feature = "std", all(target_arch = "wasm32", target_os = "unknown")))]
+fn system_now() -> SystemTime {
+    UNIX_EPOCH + Duration::from_millis(js_sys::Date::now() as u64)
+}
+
+#[cfg(all(
+    feature = "std",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+fn get_pid() -> u16 {
+    fold_pid(container_pid().unwrap_or_else(|_| process::id()))
+}
+
+// There are no processes, each instance gets a random pid.
+#[cfg(all(feature = "std", all(target_arch = "wasm32", target_os = "unknown")))]
+fn get_pid() -> u16 {
+    thread_rng().gen()
+}
+
+// Only 2 bytes of the pid are stored, xor-ing both halves keeps the entropy of
+// the upper half (e.g. from the cpuset checksum) which truncating would drop.
+// Pids below 65536 are left unchanged.
+#[cfg(feature = "std")]
+fn fold_pid(pid: u32) -> u16 {
+    (pid >> 16) as u16 ^ pid as u16
+}
+
+// If /proc/self/cpuset exists and is not /, we can assume that we are in a
+// form of container and use the content of cpuset xor-ed with the PID in
+// order get a reasonable machine global unique PID.
+#[cfg(all(
+    feature = "std",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+fn container_pid() -> Result<u32, io::Error> {
+    let buff = fs::read("/proc/self/cpuset")?;
+
+    let mut hasher = Hasher::new();
+    hasher.update(buff.as_slice());
+    let checksum = hasher.finalize();
+
+    Ok(process::id() ^ checksum)
+}
+
+// Pid for `new_generator_checked`: only Linux has a cpuset to check, other
+// platforms use the raw process id, which is not a fallback there.
+#[cfg(all(feature = "std", target_os = "linux"))]
+fn checked_pid() -> Result<u32, io::Error> {
+    container_pid()
+}
+
+#[cfg(all(
+    feature = "std",
+    not(target_os = "linux"),
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+fn checked_pid() -> Result<u32, io::Error> {
+    Ok(process::id())
+}
+
+// There are no processes, the pid would be random.
+#[cfg(all(feature = "std", all(target_arch = "wasm32", target_os = "unknown")))]
+fn checked_pid() -> Result<u32, io::Error> {
+    Err(io::Error::from(io::ErrorKind::Unsupported))
+}
+
+// Returns the given machine id, or random bytes flagged with `true` when
+// there is none.
+#[cfg(feature = "std")]
+fn read_machine_id<R: RngCore>(id: Option<[u8; 3]>, rng: &mut R) -> ([u8; 3], bool) {
+    match id {
+        Some(id) => (id, false),
+        None => {
+            let mut buff = [0u8; 3];
+            rng.fill_bytes(&mut buff);
+            (buff, true)
+        },
+    }
+}
+
+#[cfg(feature = "std")]
+fn detect_machine_id() -> Option<[u8; 3]> {
+    if let Some(id) = env::var("XID_MACHINE_ID")
+        .ok()
+        .and_then(|x| parse_machine_id(&x))
+    {
+        return Some(id);
+    }
+
+    let id = match platform_machine_id() {
+        Ok(x) => match valid_platform_id(&x) {
+            Some(x) => x.to_string(),
+            None => hostname(),
+        },
+
+        _ => hostname(),
+    };
+
+    if id.is_empty() {
+        return None;
+    }
+
+    Some(machine_id_from_str(&id))
+}
+
+// Platform machine ids are at least 32 chars long (/etc/machine-id, UUIDs are
+// 36 chars), a shorter value comes from a degenerate or truncated file and
+// could be the same on many hosts.
+#[cfg(feature = "std")]
+const MIN_PLATFORM_ID_LEN: usize = 32;
+
+// Returns the trimmed platform machine id, or `None` when it is too short to
+// be trusted.
+#[cfg(feature = "std")]
+fn valid_platform_id(raw: &str) -> Option<&str> {
+    let id = raw.trim();
+
+    if id.len() < MIN_PLATFORM_ID_LEN {
+        return None;
+    }
+
+    Some(id)
+}
+
+// Parses a machine id given as 6 hexadecimal digits (e.g. `XID_MACHINE_ID`),
+// anything else is ignored.
+#[cfg(feature = "std")]
+fn parse_machine_id(input: &str) -> Option<[u8; 3]> {
+    let input = input.trim();
+
+    if input.len() != 6 || !input.bytes().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let x = u32::from_str_radix(input, 16).ok()?;
+
+    Some([(x >> 16) as u8, (x >> 8) as u8, x as u8])
+}
+
+#[cfg(all(feature = "std", target_os = "linux"))]
+fn platform_machine_id() -> Result<String, io::Error> {
+    read_first_valid(&[
+        // world readable and stable, as used by systemd
+        "/etc/machine-id",
+        "/var/lib/dbus/machine-id",
+        // XXX: unlikely to work if read with an unpriviledged user
+        "/sys/class/dmi/id/product_uuid",
+    ])
+}
+
+#[cfg(all(feature = "std", target_os = "macos"))]
+fn platform_machine_id() -> Result<String, io::Error> {
+    // absolute path, so that no other ioreg found in PATH is run
+    let output = process::Command::new("/usr/sbin/ioreg")
+        .args(["-rd1", "-c", "IOPlatformExpertDevice"])
+        .output()?;
+
+    parse_ioreg_uuid(&String::from_utf8_lossy(&output.stdout))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "IOPlatformUUID not found"))
+}
+
+#[cfg(all(feature = "std", target_os = "windows"))]
+fn platform_machine_id() -> Result<String, io::Error> {
+    // KEY_WOW64_64KEY: 32 bits processes would otherwise read the redirected
+    // WOW6432Node key, which has no MachineGuid
+    RegKey::predef(HKEY_LOCAL_MACHINE)
+        .open_subkey_with_flags(
+            r"SOFTWARE\Microsoft\Cryptography",
+            KEY_QUERY_VALUE | KEY_WOW64_64KEY,
+        )?
+        .get_value("MachineGuid")
+}
+
+// The hostname is used on the other platforms.
+#[cfg(all(
+    feature = "std",
+    not(any(target_os = "linux", target_os = "macos", target_os = "windows"))
+))]
+fn platform_machine_id() -> Result<String, io::Error> {
+    Err(io::Error::other("no platform machine id available"))
+}
+
+// Extracts the value of `"IOPlatformUUID" = "..."` from the output of ioreg.
+#[cfg(all(feature = "std", any(target_os = "macos", test)))]
+fn parse_ioreg_uuid(output: &str) -> Option<String> {
+    output
+        .lines()
+        .find(|line| line.contains("\"IOPlatformUUID\""))
+        .and_then(|line| line.split('=').nth(1))
+        .map(|value| value.trim().trim_matches('"').to_string())
+        .filter(|value| !value.is_empty())
+}
+
+// Returns the content of the first file of `paths` which can be read and
+// holds a valid platform machine id (see `valid_platform_id`).
+#[cfg(all(feature = "std", any(target_os = "linux", test)))]
+fn read_first_valid(paths: &[&str]) -> Result<String, io::Error> {
+    let mut err = io::Error::new(io::ErrorKind::NotFound, "no machine id file found");
+
+    for path in paths {
+        match fs::File::open(path) {
+            Ok(mut file) => {
+                let mut contents = String::new();
+
+                match file.read_to_string(&mut contents) {
+                    Ok(_) if valid_platform_id(&contents).is_some() => return Ok(contents),
+                    Ok(_) => {},
+                    Err(e) => err = e,
+                }
+            },
+            Err(e) => err = e,
+        }
+    }
+
+    Err(err)
+}
+
+#[cfg(all(
+    feature = "std",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+fn hostname() -> String {
+    hostname_string(gethostname())
+}
+
+// No hostname either, an empty one makes the machine id random.
+#[cfg(all(feature = "std", all(target_arch = "wasm32", target_os = "unknown")))]
+fn hostname() -> String {
+    String::new()
+}
+
+// Invalid UTF-8 sequences are replaced rather than failing, the machine id is
+// only a hash of the hostname anyway.
+#[cfg(all(
+    feature = "std",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+fn hostname_string(name: std::ffi::OsString) -> String {
+    name.to_string_lossy().into_owned()
+}
+
+// ---
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use indexmap::IndexMap;
+    use proptest::prelude::{any, prop_assert, prop_assert_eq, prop_oneof, proptest, Just};
+    use rand::rngs::StdRng;
+    use std::collections::{HashMap, HashSet};
+    use std::convert::TryInto;
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Instant;
+
+    #[test]
+    fn test_basic() {
+        let total = 1e6 as u32;
+
+        let g = new_generator();
+
+        let mut previous_counter = 0;
+        let mut previous_id = g.new_id().unwrap();
+
+        for i in 0..total {
+            let id = g.new_id().unwrap();
+
+            assert!(
+                previous_id < id,
+                "{} ({:?}) != {} ({:?}) {}",
+                previous_id.encode(),
+                previous_id,
+                id.encode(),
+                id,
+                i
+            );
+
+            if i > 0 {
+                assert_eq!(id.counter(), (previous_counter + 1) & 0xFF_FFFF);
+            }
+
+            previous_counter = id.counter();
+
+            let x = id.encode();
+            assert_eq!(x.len(), 20);
+            assert_eq!(id.machine(), g.machine_id);
+
+            previous_id = id;
+        }
+    }
+
+    #[test]
+    fn test_generation_speed() {
+        let total = 1e6 as u32;
+
+        let g = new_generator();
+
+        let start = Instant::now();
+
+        for _ in 0..total {
+            g.new_id().unwrap();
+        }
+
+        let elapsed = start.elapsed().as_secs_f64();
+
+        let limit = 0.5;
+
+        assert!(
+            elapsed <= limit,
+            "Must generated {} ids id less than {} second, took {} seconds",
+            total,
+            limit,
+            elapsed
+        );
+    }
+
+    #[test]
+    fn test_encoding_speed() {
+        let total = 1e6 as u32;
+
+        let g = new_generator();
+
+        let mut buff: Vec<ID> = Vec::with_capacity(total as usize);
+
+        for _ in 0..total {
+            buff.push(g.new_id().unwrap());
+        }
+
+        let start = Instant::now();
+
+        for id in buff.into_iter() {
+            id.encode();
+        }
+
+        let elapsed = start.elapsed().as_secs_f64();
+
+        let limit = 1.5;
+
+        assert!(
+            elapsed <= limit,
+            "Must encode {} ids id less than {} second, took {} seconds",
+            total,
+            limit,
+            elapsed
+        );
+    }
+
+    #[test]
+    fn test_eq() {
+        let g = new_generator();
+
+        let a = g.new_id().unwrap();
+        let b = g.new_id().unwrap();
+        let c = g.new_id().unwrap();
+
+        assert!(a == a);
+        assert!(a <= a);
+        assert!(a != b);
+        assert!(a != c);
+
+        assert!(a < b);
+        assert!(b > a);
+        assert!(b >= a);
+
+        assert!(b < c);
+        assert!(c > b);
+
+        assert!(a < c);
+        assert!(c > a);
+    }
+
+    #[test]
+    fn test_from() {
+        let g = new_generator();
+
+        let a = g.new_id().unwrap();
+
+        let b = ID::try_from(a.encode().as_str()).unwrap();
+
+        assert_eq!(a.val, b.val);
+        assert_eq!(a.encode(), b.encode());
+
+        let c: ID = String::from(a).try_into().unwrap();
+        assert_eq!(a, c);
+
+        let encoded: String = a.into();
+        assert_eq!(encoded, a.encode());
+
+        assert_eq!(
+            ID::try_from("invalid"),
+            Err(DecodeError::InvalidLength {
+                expected: 20,
+                actual: 7
+            })
+        );
+        assert_eq!(
+            ID::try_from(String::from("9m4e2mr0ui3e8a215n4h")),
+            Err(DecodeError::NonCanonical)
+        );
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        let total = 1e6 as u32;
+
+        let g = new_generator();
+
+        for _ in 0..total {
+            let id = g.new_id().unwrap();
+
+            assert_eq!(id, ID::decode(&id.encode()).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_decoding_speed() {
+        let total = 1e6 as u32;
+
+        let g = new_generator();
+
+        let mut buff: Vec<String> = Vec::with_capacity(total as usize);
+
+        for _ in 0..total {
+            let id = g.new_id().unwrap();
+
+            buff.push(id.encode().clone());
+
+            assert_eq!(id, ID::decode(&id.encode()).unwrap());
+        }
+
+        // ----
+
+        let start = Instant::now();
+
+        for encoded in buff.into_iter() {
+            ID::decode(&encoded).unwrap();
+        }
+
+        let elapsed = start.elapsed().as_secs_f64();
+
+        let limit = 0.5;
+
+        assert!(
+            elapsed <= limit,
+            "Must decode {} ids in less than {} second, took {} seconds",
+            total,
+            limit,
+            elapsed
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_json() {
+        let g = new_generator();
+
+        let src = g.new_id().unwrap();
+
+        let serialized = serde_json::to_string(&src).unwrap();
+
+        let deserialized: ID = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(src, deserialized);
+
+        assert_eq!(serialized, format!("\"{}\"", src.encode()));
+
+        assert!(serde_json::from_str::<ID>("\"invalid\"").is_err());
+        assert!(serde_json::from_str::<ID>("\"9m4e2mr0ui3e8a215n4h\"").is_err());
+        assert!(serde_json::from_str::<ID>("42").is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_binary() {
+        let id = ID::decode("9m4e2mr0ui3e8a215n4g").unwrap();
+
+        let encoded = bincode::serialize(&id).unwrap();
+        assert_eq!(encoded[..8], 12u64.to_le_bytes());
+        assert_eq!(&encoded[8..], id.as_bytes());
+        assert_eq!(bincode::deserialize::<ID>(&encoded).unwrap(), id);
+
+        // ids serialized as strings are still readable
+        let legacy = bincode::serialize("9m4e2mr0ui3e8a215n4g").unwrap();
+        assert_eq!(bincode::deserialize::<ID>(&legacy).unwrap(), id);
+
+        let invalid = bincode::serialize("9m4e2mr0ui3e8a215n4h").unwrap();
+        assert!(bincode::deserialize::<ID>(&invalid).is_err());
+
+        let short = bincode::serialize(&[1u8, 2, 3][..]).unwrap();
+        assert!(bincode::deserialize::<ID>(&short).is_err());
+    }
+
+    #[test]
+    fn test_hash_map_reference_no_encode() {
+        let g = new_generator();
+        let id = g.new_id().unwrap();
+        let mut map: HashMap<&ID, String> = HashMap::new();
+
+        map.insert(&id, String::from("test"));
+        let value = map.get(&id);
+
+        assert!(value.is_some());
+        assert_eq!("test", value.unwrap());
+    }
+
+    #[test]
+    fn test_hash_map_reference_encode() {
+        let g = new_generator();
+        let id = g.new_id().unwrap();
+        let mut map: HashMap<&ID, String> = HashMap::new();
+
+        let encoded = id.encode();
+        map.insert(&id, String::from("test"));
+
+        let id = ID::decode(&encoded).unwrap();
+        let value = map.get(&id);
+
+        assert!(value.is_some());
+        assert_eq!("test", value.unwrap());
+    }
+
+    #[test]
+    fn test_hash_map_no_reference() {
+        let g = new_generator();
+        let id = g.new_id().unwrap();
+        let mut map: HashMap<ID, String> = HashMap::new();
+
+        let encoded = id.encode();
+        map.insert(id, String::from("test"));
+
+        let id = ID::decode(&encoded).unwrap();
+        let value = map.get(&id);
+
+        assert!(value.is_some());
+        assert_eq!("test", value.unwrap());
+    }
+
+    #[test]
+    fn test_cmp_str() {
+        let g = new_generator();
+
+        let a = g.new_id().unwrap();
+        let b = g.new_id().unwrap();
+        let c = g.new_id().unwrap();
+
+        let encoded = b.encode();
+
+        assert!(b == *encoded.as_str());
+        assert!(b == encoded);
+        assert!(a != encoded);
+
+        assert!(a < encoded);
+        assert!(c > encoded);
+        assert!(b <= encoded);
+        assert!(b >= encoded);
+
+        assert_eq!(
+            a.partial_cmp(&encoded),
+            Some(a.cmp(&ID::decode(&encoded).unwrap()))
+        );
+
+        for invalid in &[
+            "",
+            "invalid",
+            "zzzzzzzzzzzzzzzzzzzz",
+            "9m4e2mr0ui3e8a215n4gx",
+        ] {
+            let invalid = invalid.to_string();
+
+            assert!(b != invalid);
+            assert_eq!(b.partial_cmp(&invalid), None);
+            assert!(!b.lt(&invalid));
+            assert!(!b.gt(&invalid));
+        }
+    }
+
+    #[test]
+    fn test_short() {
+        let g = new_generator();
+
+        let id = g.new_id().unwrap();
+        let short = id.short();
+
+        assert_eq!(short.len(), 8);
+        assert!(id.encode().starts_with(&short));
+    }
+
+    #[test]
+    fn test_from_seed() {
+        let a = Generator::from_seed(42);
+        let b = Generator::from_seed(42);
+
+        assert_eq!(a.machine_id, b.machine_id);
+        assert_eq!(a.pid(), b.pid());
+
+        let t = SystemTime::now();
+
+        for _ in 0..1000 {
+            assert_eq!(
+                a.new_id_with_time(t).unwrap(),
+                b.new_id_with_time(t).unwrap()
+            );
+        }
+
+        let c = Generator::from_seed(43);
+        assert_ne!(
+            a.new_id_with_time(t).unwrap(),
+            c.new_id_with_time(t).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_debug() {
+        let g = Generator::from_seed(42);
+
+        let id = g.new_id().unwrap();
+
+        let machine = format!("{:?}", id.machine());
+        let pid = format!("pid: {}", id.pid());
+
+        let redacted = format!("{:?}", id);
+        assert!(redacted.contains(&id.encode()));
+        assert!(!redacted.contains(&machine));
+        assert!(!redacted.contains(&pid));
+        assert!(!redacted.contains(&format!("{:?}", id.val)));
+
+        let full = format!("{:#?}", id);
+        assert!(full.contains(&id.encode()));
+        assert!(full.contains("machine: ["));
+        assert!(full.contains(&pid));
+    }
+
+    #[test]
+    fn test_analyze() {
+        assert_eq!(analyze(&[]), Analysis::default());
+
+        let a = Generator::from_seed(1);
+        let b = Generator::from_seed(2);
+
+        let mut ids = Vec::new();
+
+        for (secs, count) in &[(1000, 3), (1001, 7), (1002, 7), (1003, 1)] {
+            let t = UNIX_EPOCH + Duration::from_secs(*secs);
+
+            for i in 0..*count {
+                let g = if i % 2 == 0 { &a } else { &b };
+                ids.push(g.new_id_with_time(t).unwrap());
+            }
+        }
+
+        assert_eq!(
+            analyze(&ids),
+            Analysis {
+                machines: 2,
+                pids: 2,
+                peak_per_second: 7,
+                hottest_second: 1001,
+            }
+        );
+    }
+
+    #[test]
+    fn test_id_list() {
+        let g = new_generator();
+
+        let empty = IdList::try_from("").unwrap();
+        assert_eq!(empty, IdList(Vec::new()));
+        assert_eq!(empty.encode(), "");
+
+        let a = g.new_id().unwrap();
+        let single: IdList = a.encode().parse().unwrap();
+        assert_eq!(single, IdList(vec![a]));
+        assert_eq!(single.encode(), a.encode());
+
+        let ids = IdList(vec![a, g.new_id().unwrap(), g.new_id().unwrap()]);
+        let encoded = ids.to_string();
+        assert_eq!(encoded.matches(',').count(), 2);
+        assert_eq!(IdList::try_from(encoded.as_str()).unwrap(), ids);
+
+        for invalid in &[
+            ",",
+            "invalid",
+            &format!("{},", encoded),
+            &format!("{},x", encoded),
+        ] {
+            assert!(IdList::try_from(*invalid).is_err());
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_id_list_json() {
+        let g = new_generator();
+
+        let ids = IdList(vec![g.new_id().unwrap(), g.new_id().unwrap()]);
+
+        let serialized = serde_json::to_string(&ids).unwrap();
+        assert_eq!(serialized, format!("\"{}\"", ids.encode()));
+
+        let deserialized: IdList = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(deserialized, ids);
+
+        assert!(serde_json::from_str::<IdList>("\"invalid\"").is_err());
+    }
+
+    #[test]
+    fn test_counter_round_trip() {
+        let g = Generator::from_seed(42);
+
+        let check = |counter: u32| {
+            g.reset_counter(counter);
+
+            let id = g.new_id_with_timestamp(0).unwrap();
+
+            assert_eq!(id.counter(), counter & 0xFF_FFFF, "counter: {}", counter);
+
+            let id = ID::from_parts(0, [0; 3], 0, counter);
+
+            assert_eq!(id.counter(), counter & 0xFF_FFFF, "counter: {}", counter);
+        };
+
+        // every value around each 24 bits boundary, and a wide sample of the
+        // whole 32 bits range in between
+        for boundary in (0..=u32::MAX >> 24).map(|x| x << 24) {
+            for offset in 0..1024 {
+                check(boundary.wrapping_sub(512).wrapping_add(offset));
+            }
+        }
+
+        for counter in (0..=u32::MAX).step_by(65_521) {
+            check(counter);
+        }
+
+        check(u32::MAX);
+    }
+
+    #[test]
+    fn test_generate_spanning() {
+        let g = new_generator();
+
+        let start = UNIX_EPOCH + Duration::from_secs(1_500_000_000);
+        let end = start + Duration::from_secs(3600);
+
+        let ids = g.generate_spanning(start, end, 1000).unwrap();
+        assert_eq!(ids.len(), 1000);
+
+        assert_eq!(ids.first().unwrap().time(), start);
+        assert_eq!(ids.last().unwrap().time(), end);
+
+        for w in ids.windows(2) {
+            assert!(w[0] < w[1]);
+            assert!(w[0].time() <= w[1].time());
+        }
+
+        for id in &ids {
+            assert!(id.time() >= start && id.time() <= end);
+        }
+
+        let single = g.generate_spanning(start, end, 1).unwrap();
+        assert_eq!(single.len(), 1);
+        assert_eq!(single[0].time(), start);
+
+        assert!(g.generate_spanning(start, end, 0).unwrap().is_empty());
+        assert!(g.generate_spanning(end, start, 10).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_preserves_order() {
+        let g = new_generator();
+
+        let mut ids = g
+            .generate_spanning(UNIX_EPOCH, SystemTime::now(), 1000)
+            .unwrap();
+        ids.sort();
+
+        let json: Vec<ID> = serde_json::from_str(&serde_json::to_string(&ids).unwrap()).unwrap();
+        assert_eq!(json, ids);
+
+        let binary: Vec<ID> = bincode::deserialize(&bincode::serialize(&ids).unwrap()).unwrap();
+        assert_eq!(binary, ids);
+        assert_eq!(
+            bincode::serialize(&ids).unwrap().len(),
+            8 + ids.len() * (8 + ID_LEN)
+        );
+
+        for w in binary.windows(2) {
+            assert!(w[0] < w[1]);
+        }
+    }
+
+    #[test]
+    fn test_with_pid_bytes() {
+        let g = new_generator().with_pid_bytes([0xAB, 0xCD]);
+
+        let id = g.new_id().unwrap();
+
+        assert_eq!(id.val[7], 0xAB);
+        assert_eq!(id.val[8], 0xCD);
+        assert_eq!(id.pid_bytes(), [0xAB, 0xCD]);
+        assert_eq!(id.pid(), 0xABCD);
+        assert_eq!(id.machine(), g.machine_id);
+    }
+
+    #[test]
+    fn test_index_map() {
+        let g = new_generator();
+
+        let ids: Vec<ID> = (0..100).map(|_| g.new_id().unwrap()).rev().collect();
+
+        let mut map = IndexMap::new();
+
+        for (i, id) in ids.iter().enumerate() {
+            map.insert(*id, i);
+        }
+
+        assert_eq!(map.keys().cloned().collect::<Vec<ID>>(), ids);
+        assert_eq!(map.get(&ids[42]), Some(&42));
+    }
+
+    #[test]
+    fn test_btree_map() {
+        let g = new_generator();
+
+        let start = UNIX_EPOCH + Duration::from_secs(1_500_000_000);
+
+        let ids = g
+            .generate_spanning(start, start + Duration::from_secs(9), 100)
+            .unwrap();
+
+        let mut map = BTreeMap::new();
+
+        for id in ids.iter().rev() {
+            map.insert(*id, id.time());
+        }
+
+        assert_eq!(map.keys().cloned().collect::<Vec<ID>>(), ids);
+
+        // every id generated during seconds 3, 4 and 5
+        let window: Vec<&ID> = map
+            .range(
+                ID::min_for_time(start + Duration::from_secs(3))
+                    ..=ID::max_for_time(start + Duration::from_secs(5)),
+            )
+            .map(|x| x.0)
+            .collect();
+
+        let expected: Vec<&ID> = ids
+            .iter()
+            .filter(|x| {
+                x.time() >= start + Duration::from_secs(3)
+                    && x.time() <= start + Duration::from_secs(5)
+            })
+            .collect();
+
+        assert!(!window.is_empty());
+        assert_eq!(window, expected);
+    }
+
+    #[test]
+    fn test_from_reader() {
+        let g = new_generator();
+
+        let ids: Vec<ID> = (0..5).map(|_| g.new_id().unwrap()).collect();
+
+        let mut buff: Vec<u8> = ids.iter().flat_map(|x| x.val.to_vec()).collect();
+        // trailing garbage, shorter than an id
+        buff.extend_from_slice(&[1, 2, 3]);
+
+        let mut r = io::Cursor::new(buff);
+
+        let mut read = Vec::new();
+        while let Ok(id) = ID::from_reader(&mut r) {
+            read.push(id);
+        }
+
+        assert_eq!(read, ids);
+
+        let err = ID::from_reader(&mut io::Cursor::new(Vec::new())).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn test_uuid() {
+        let id = ID::decode("9m4e2mr0ui3e8a215n4g").unwrap();
+
+        let u = id.to_uuid();
+        assert_eq!(u.to_string(), "4d88e15b-60f4-86e4-2841-2dc900000000");
+        assert_eq!(ID::from_uuid(u), Some(id));
+
+        // ordering is kept
+        let g = new_generator();
+        let (a, b) = (g.new_id().unwrap(), g.new_id().unwrap());
+        assert!(a.to_uuid() < b.to_uuid());
+
+        let random = uuid::Uuid::from_u128(0x67e5_5044_10b1_426f_9247_bb68_0e5f_e0c8);
+        assert_eq!(ID::from_uuid(random), None);
+        assert_eq!(
+            ID::from_uuid(uuid::Uuid::from_bytes([
+                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1
+            ])),
+            None
+        );
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_storage_key() {
+        let g = new_generator();
+
+        // 2019-06-15T13:20:00Z
+        let id = g
+            .new_id_with_time(UNIX_EPOCH + Duration::from_secs(1_560_604_800))
+            .unwrap();
+
+        assert_eq!(
+            id.storage_key(Granularity::Day),
+            format!("2019/06/15/{}", id.encode())
+        );
+
+        assert_eq!(
+            id.storage_key(Granularity::Hour),
+            format!("2019/06/15/13/{}", id.encode())
+        );
+    }
+
+    #[test]
+    fn test_merge_sorted() {
+        let g = Generator::from_seed(42);
+
+        let ids: Vec<ID> = (0..30).map(|_| g.new_id().unwrap()).collect();
+
+        let a: Vec<ID> = ids.iter().step_by(3).cloned().collect();
+        let b: Vec<ID> = ids.iter().skip(1).step_by(3).cloned().collect();
+        // shares some ids with a
+        let c: Vec<ID> = ids
+            .iter()
+            .skip(2)
+            .step_by(3)
+            .chain(a.iter().take(4))
+            .cloned()
+            .collect();
+        let mut c = c;
+        c.sort();
+
+        let merged = merge_sorted(vec![a.clone(), b.clone(), c.clone(), Vec::new()], true);
+        assert_eq!(merged, ids);
+
+        let merged = merge_sorted(vec![a, b, c], false);
+        assert_eq!(merged.len(), 34);
+        for w in merged.windows(2) {
+            assert!(w[0] <= w[1]);
+        }
+
+        assert!(merge_sorted(Vec::new(), true).is_empty());
+    }
+
+    #[test]
+    fn test_decode() {
+        let g = new_generator();
+
+        let id = g.new_id().unwrap();
+        assert_eq!(ID::decode(&id.encode()).unwrap(), id);
+        assert_eq!(id.encode().parse::<ID>().unwrap(), id);
+
+        let id = ID::decode("9m4e2mr0ui3e8a215n4g").unwrap();
+        assert_eq!(id.encode(), "9m4e2mr0ui3e8a215n4g");
+
+        assert_eq!(
+            ID::decode(""),
+            Err(DecodeError::InvalidLength {
+                expected: 20,
+                actual: 0
+            })
+        );
+        assert_eq!(
+            ID::decode("9m4e2mr0ui3e8a215n4"),
+            Err(DecodeError::InvalidLength {
+                expected: 20,
+                actual: 19
+            })
+        );
+        assert_eq!(
+            ID::decode("9m4e2mr0ui3e8a215n4gg"),
+            Err(DecodeError::InvalidLength {
+                expected: 20,
+                actual: 21
+            })
+        );
+        assert_eq!(
+            ID::decode("9m4e2mr0ui3e8a215n4w"),
+            Err(DecodeError::InvalidCharacter {
+                position: 19,
+                character: 'w',
+            })
+        );
+        assert_eq!(
+            ID::decode("9W4e2mr0ui3e8a215n4g"),
+            Err(DecodeError::InvalidCharacter {
+                position: 1,
+                character: 'W',
+            })
+        );
+        assert_eq!(
+            "9m4e2mr0ui3e8a215n4h".parse::<ID>(),
+            Err(DecodeError::NonCanonical)
+        );
+    }
+
+    #[test]
+    fn test_bytes() {
+        let g = new_generator();
+
+        let id = g.new_id().unwrap();
+
+        assert_eq!(id.as_bytes(), &id.val);
+        assert_eq!(ID::from_bytes(*id.as_bytes()), id);
+        assert_eq!(ID::from_bytes(id.into_bytes()), id);
+
+        let id = ID::from_bytes([
+            0x4d, 0x88, 0xe1, 0x5b, 0x60, 0xf4, 0x86, 0xe4, 0x28, 0x41, 0x2d, 0xc9,
+        ]);
+
+        assert_eq!(id.encode(), "9m4e2mr0ui3e8a215n4g");
+        assert_eq!(id.machine(), [0x60, 0xf4, 0x86]);
+        assert_eq!(id.pid(), 0xe428);
+        assert_eq!(id.counter(), 0x412dc9);
+    }
+
+    #[test]
+    fn test_copy() {
+        let g = new_generator();
+
+        let a = g.new_id().unwrap();
+        let b = a;
+
+        // a is still usable after being moved
+        assert_eq!(a, b);
+        assert_eq!(a.clone(), b);
+    }
+
+    #[test]
+    fn test_hash_set() {
+        let g = new_generator();
+
+        let ids: Vec<ID> = (0..100).map(|_| g.new_id().unwrap()).collect();
+
+        let mut set: HashSet<ID> = ids.iter().cloned().collect();
+        assert_eq!(set.len(), ids.len());
+
+        for id in &ids {
+            // an equal id rebuilt from its bytes hashes the same way
+            assert!(set.contains(&ID::from_bytes(*id.as_bytes())));
+            assert!(!set.insert(ID::decode(&id.encode()).unwrap()));
+        }
+    }
+
+    #[test]
+    fn test_display() {
+        let g = new_generator();
+
+        let id = g.new_id().unwrap();
+
+        assert_eq!(id.to_string(), id.encode());
+        assert_eq!(format!("/ids/{}", id), format!("/ids/{}", id.encode()));
+    }
+
+    #[test]
+    fn test_encode_matches_base32hex() {
+        // straightforward bit by bit base32 hex encoding, without padding
+        let reference = |val: &[u8; ID_LEN]| -> String {
+            let alphabet = "0123456789abcdefghijklmnopqrstuv".as_bytes();
+
+            let mut bits: Vec<u8> = val
+                .iter()
+                .flat_map(|b| (0..8).rev().map(move |i| (b >> i) & 1))
+                .collect();
+            bits.resize(100, 0);
+
+            bits.chunks(5)
+                .map(|c| alphabet[c.iter().fold(0, |acc, b| acc << 1 | *b as usize)] as char)
+                .collect()
+        };
+
+        let mut rng = thread_rng();
+
+        for _ in 0..10_000 {
+            let mut val = [0u8; ID_LEN];
+            rng.fill_bytes(&mut val);
+
+            let id = ID::from_bytes(val);
+
+            assert_eq!(id.encode(), reference(&val));
+            assert_eq!(ID::decode(&id.encode()).unwrap(), id);
+        }
+    }
+
+    #[test]
+    fn test_with_machine_id() {
+        let g = new_generator().with_machine_id([1, 2, 3]);
+
+        let id = g.new_id().unwrap();
+
+        assert_eq!(id.machine(), [1, 2, 3]);
+        assert_eq!(id.pid(), get_pid());
+    }
+
+    #[test]
+    fn test_builder() {
+        let g = GeneratorBuilder::new()
+            .machine_id([1, 2, 3])
+            .pid(0xABCD)
+            .counter(41)
+            .build();
+
+        let t = UNIX_EPOCH + Duration::from_secs(1_500_000_000);
+
+        let a = g.new_id_with_time(t).unwrap();
+        assert_eq!(a.machine(), [1, 2, 3]);
+        assert_eq!(a.pid(), 0xABCD);
+        assert_eq!(a.counter(), 41);
+
+        let b = g.new_id_with_time(t).unwrap();
+        assert_eq!(b.counter(), 42);
+
+        // same settings, same ids
+        let h = GeneratorBuilder::new()
+            .machine_id([1, 2, 3])
+            .pid(0xABCD)
+            .counter(41)
+            .build();
+        assert_eq!(h.new_id_with_time(t).unwrap(), a);
+
+        let h = Generator::builder()
+            .machine_id([1, 2, 3])
+            .pid(0xABCD)
+            .counter_start(41)
+            .build();
+        assert_eq!(h.new_id_with_time(t).unwrap(), a);
+
+        // unset fields are auto-detected
+        let d = GeneratorBuilder::new().pid(7).build();
+        let id = d.new_id().unwrap();
+        assert_eq!(id.machine(), detect_machine_id().unwrap());
+        assert_eq!(id.pid(), 7);
+
+        let id = GeneratorBuilder::new()
+            .counter(1 << 24 | 5)
+            .build()
+            .new_id()
+            .unwrap();
+        assert_eq!(id.counter(), 5);
+    }
+
+    #[test]
+    fn test_counter_exhausted() {
+        let g = Generator::from_seed(42);
+
+        let ts = 1_500_000_000u64;
+        let t = UNIX_EPOCH + Duration::from_secs(ts);
+
+        // pretend that all the seconds which can be borrowed were used
+        g.state.store(
+            (ts + MAX_CARRY) << 32 | MAX_CARRY << 24 | (COUNTER_MAX - 2),
+            Ordering::SeqCst,
+        );
+
+        assert!(g.new_id_with_time(t).is_ok());
+        assert!(matches!(
+            g.new_id_with_time(t),
+            Err(Error::CounterExhausted)
+        ));
+        assert!(matches!(
+            g.new_id_with_time(t),
+            Err(Error::CounterExhausted)
+        ));
+
+        // seconds past the carried range have a fresh counter space
+        assert!(g
+            .new_id_with_time(t + Duration::from_secs(MAX_CARRY + 1))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_counter_masked() {
+        let g = Generator::from_seed(42);
+
+        g.reset_counter(0xFF_FFFF);
+
+        assert_eq!(g.new_id().unwrap().counter(), 0xFF_FFFF);
+        assert_eq!(g.new_id().unwrap().counter(), 0);
+        assert_eq!(g.new_id().unwrap().counter(), 1);
+
+        // contiguous across the wrap around, whatever the initial value
+        for start in &[0xFF_FFF0, 0x1FF_FFF0, u32::MAX - 15] {
+            g.reset_counter(*start);
+
+            let counters: Vec<u32> = (0..32)
+                .map(|_| g.new_id_with_timestamp(1_500_000_000).unwrap().counter())
+                .collect();
+
+            for (i, c) in counters.iter().enumerate() {
+                assert_eq!(*c, (0xFF_FFF0 + i as u32) & 0xFF_FFFF);
+            }
+        }
+    }
+
+    #[test]
+    fn test_nil() {
+        let g = new_generator();
+
+        let nil = ID::nil();
+
+        assert!(nil.is_nil());
+        assert_eq!(nil.as_bytes(), &[0u8; ID_LEN]);
+        assert_eq!(nil.encode(), "00000000000000000000");
+        assert_eq!(ID::decode("00000000000000000000").unwrap(), nil);
+
+        let id = g.new_id().unwrap();
+        assert!(!id.is_nil());
+        assert!(nil < id);
+        assert_ne!(nil, id);
+    }
+
+    #[test]
+    fn test_default() {
+        #[derive(Default)]
+        struct Row {
+            id: ID,
+        }
+
+        assert!(ID::default().is_nil());
+        assert_eq!(Row::default().id, ID::nil());
+    }
+
+    #[test]
+    fn test_shared_generator() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Generator>();
+
+        let g = Arc::new(new_generator());
+
+        let handles: Vec<thread::JoinHandle<Vec<ID>>> = (0..8)
+            .map(|_| {
+                let g = Arc::clone(&g);
+                thread::spawn(move || (0..10_000).map(|_| g.new_id().unwrap()).collect())
+            })
+            .collect();
+
+        let mut ids = HashSet::new();
+
+        for handle in handles {
+            for id in handle.join().unwrap() {
+                assert!(ids.insert(id));
+            }
+        }
+
+        assert_eq!(ids.len(), 80_000);
+    }
+
+    #[test]
+    fn test_validate() {
+        let g = new_generator();
+
+        let encoded = g.new_id().unwrap().encode();
+        assert!(is_valid(&encoded));
+        assert_eq!(validate(&encoded), Ok(()));
+
+        for invalid in &[
+            "",
+            "9m4e2mr0ui3e8a215n4",
+            "9m4e2mr0ui3e8a215n4gg",
+            "9m4e2mr0ui3e8a215n4w",
+            "9M4E2MR0UI3E8A215N4W",
+            "9m4e2mr0ui3e8a215n4h",
+            "9m4e2mr0ui3e8a215né",
+        ] {
+            assert!(!is_valid(invalid));
+            assert_eq!(validate(invalid), ID::decode(invalid).map(|_| ()));
+        }
+
+        // validation and decoding always agree
+        let mut rng = thread_rng();
+        let alphabet = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+        for _ in 0..10_000 {
+            let s: String = (0..20)
+                .map(|_| alphabet[rng.gen_range(0, alphabet.len())] as char)
+                .collect();
+
+            assert_eq!(is_valid(&s), ID::decode(&s).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_clock_moving_backward() {
+        let g = new_generator();
+
+        let before = g.new_id().unwrap();
+
+        // pretend the clock was 100s ahead when the last id was generated
+        let ahead = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            + 100;
+        g.last_time.store(ahead, Ordering::SeqCst);
+
+        let a = g.new_id().unwrap();
+        let b = g.new_id().unwrap();
+
+        assert_eq!(a.time(), UNIX_EPOCH + Duration::from_secs(ahead));
+        assert_eq!(b.time(), a.time());
+        assert!(before < a);
+        assert!(a < b);
+
+        // explicit times are used as is
+        let t = UNIX_EPOCH + Duration::from_secs(1_500_000_000);
+        assert_eq!(g.new_id_with_time(t).unwrap().time(), t);
+    }
+
+    #[test]
+    fn test_mongo_object_id_hex() {
+        let id = ID::decode("9m4e2mr0ui3e8a215n4g").unwrap();
+
+        assert_eq!(id.to_mongo_object_id_hex(), "4d88e15b60f486e428412dc9");
+        assert_eq!(
+            ID::from_mongo_object_id_hex("4d88e15b60f486e428412dc9").unwrap(),
+            id
+        );
+        assert_eq!(
+            ID::from_mongo_object_id_hex("4D88E15B60F486E428412DC9").unwrap(),
+            id
+        );
+
+        let g = new_generator();
+        let id = g.new_id().unwrap();
+        assert_eq!(
+            ID::from_mongo_object_id_hex(&id.to_mongo_object_id_hex()).unwrap(),
+            id
+        );
+
+        assert_eq!(
+            ID::from_mongo_object_id_hex("4d88e15b60f486e428412dc"),
+            Err(DecodeError::InvalidLength {
+                expected: 24,
+                actual: 23
+            })
+        );
+        assert_eq!(
+            ID::from_mongo_object_id_hex("4d88e15b60f486e428412dcg"),
+            Err(DecodeError::InvalidCharacter {
+                position: 23,
+                character: 'g',
+            })
+        );
+    }
+
+    #[test]
+    fn test_u128() {
+        assert_eq!(ID::nil().to_u128(), 0);
+        assert_eq!(ID::from_u128(0), ID::nil());
+
+        let max = ID::from_bytes([0xFF; ID_LEN]);
+        assert_eq!(max.to_u128(), (1 << 96) - 1);
+        assert_eq!(ID::from_u128((1 << 96) - 1), max);
+
+        // the upper 32 bits are ignored
+        assert_eq!(ID::from_u128(u128::MAX), max);
+
+        let id = ID::decode("9m4e2mr0ui3e8a215n4g").unwrap();
+        assert_eq!(id.to_u128(), 0x4d88e15b60f486e428412dc9);
+
+        let g = new_generator();
+        for _ in 0..1000 {
+            let id = g.new_id().unwrap();
+            assert_eq!(ID::from_u128(id.to_u128()), id);
+        }
+    }
+
+    #[test]
+    fn test_new_ids() {
+        let g = Generator::from_seed(42);
+
+        assert!(g.new_ids(0).unwrap().is_empty());
+
+        let ids = g.new_ids(1000).unwrap();
+        assert_eq!(ids.len(), 1000);
+
+        for w in ids.windows(2) {
+            assert!(w[0] < w[1]);
+            assert_eq!(w[0].time(), w[1].time());
+            assert_eq!(w[0].counter() + 1, w[1].counter());
+        }
+
+        assert!(ids[999] < g.new_id().unwrap());
+    }
+
+    #[test]
+    fn test_new_id_with_timestamp() {
+        let g = GeneratorBuilder::new()
+            .machine_id([1, 2, 3])
+            .pid(4)
+            .counter(5)
+            .build();
+
+        let id = g.new_id_with_timestamp(1_500_000_000).unwrap();
+
+        assert_eq!(id.time(), UNIX_EPOCH + Duration::from_secs(1_500_000_000));
+        assert_eq!(id.machine(), [1, 2, 3]);
+        assert_eq!(id.pid(), 4);
+        assert_eq!(id.counter(), 5);
+
+        // same bytes as the equivalent `SystemTime`
+        let fork = g.clone();
+
+        for secs in &[0, 1_500_000_000, u32::MAX] {
+            assert_eq!(
+                g.new_id_with_timestamp(*secs).unwrap(),
+                fork.new_id_with_time(UNIX_EPOCH + Duration::from_secs(u64::from(*secs)))
+                    .unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_with_rng() {
+        let a = Generator::with_rng(&mut StdRng::seed_from_u64(7));
+        let b = Generator::with_rng(&mut StdRng::seed_from_u64(7));
+
+        assert_eq!(a.next_counter(), b.next_counter());
+        assert_eq!(a.machine_id, b.machine_id);
+
+        let t = UNIX_EPOCH + Duration::from_secs(1_500_000_000);
+        assert_eq!(
+            a.new_id_with_time(t).unwrap(),
+            b.new_id_with_time(t).unwrap()
+        );
+
+        let c = GeneratorBuilder::new()
+            .pid(1)
+            .build_with_rng(&mut StdRng::seed_from_u64(7));
+        assert_eq!(
+            c.next_counter(),
+            Generator::with_rng(&mut StdRng::seed_from_u64(7)).next_counter()
+        );
+    }
+
+    #[test]
+    fn test_try_from_slice() {
+        let bytes = [
+            0x4d, 0x88, 0xe1, 0x5b, 0x60, 0xf4, 0x86, 0xe4, 0x28, 0x41, 0x2d, 0xc9,
+        ];
+
+        let id = ID::from(bytes);
+        assert_eq!(id, ID::from_bytes(bytes));
+        assert_eq!(ID::try_from(&bytes[..]).unwrap(), id);
+
+        let mut buff = bytes.to_vec();
+        buff.push(0);
+
+        assert_eq!(
+            ID::try_from(&buff[..]),
+            Err(DecodeError::InvalidLength {
+                expected: 12,
+                actual: 13
+            })
+        );
+        assert_eq!(
+            ID::try_from(&buff[..11]),
+            Err(DecodeError::InvalidLength {
+                expected: 12,
+                actual: 11
+            })
+        );
+    }
+
+    #[test]
+    fn test_timestamp() {
+        let g = new_generator();
+
+        let id = g
+            .new_id_with_time(UNIX_EPOCH + Duration::from_secs(1_500_000_000))
+            .unwrap();
+        assert_eq!(id.timestamp(), 1_500_000_000);
+
+        let id = ID::decode("9m4e2mr0ui3e8a215n4g").unwrap();
+        assert_eq!(id.timestamp(), 0x4d88e15b);
+        assert_eq!(
+            id.time(),
+            UNIX_EPOCH + Duration::from_secs(u64::from(id.timestamp()))
+        );
+    }
+
+    #[test]
+    fn test_from_parts() {
+        let id = ID::from_parts(0x4d88e15b, [0x60, 0xf4, 0x86], 0xe428, 0x412dc9);
+        assert_eq!(id, ID::decode("9m4e2mr0ui3e8a215n4g").unwrap());
+
+        assert_eq!(id.timestamp(), 0x4d88e15b);
+        assert_eq!(id.machine(), [0x60, 0xf4, 0x86]);
+        assert_eq!(id.pid(), 0xe428);
+        assert_eq!(id.counter(), 0x412dc9);
+
+        // the counter is masked to 24 bits
+        let id = ID::from_parts(1, [1, 2, 3], 4, 0xAB00_0005);
+        assert_eq!(id.counter(), 5);
+
+        let g = Generator::from_seed(42);
+        let t = UNIX_EPOCH + Duration::from_secs(1_500_000_000);
+        let id = g.new_id_with_time(t).unwrap();
+        assert_eq!(
+            ID::from_parts(id.timestamp(), id.machine(), id.pid(), id.counter()),
+            id
+        );
+    }
+
+    #[test]
+    fn test_new_generator_checked() {
+        let expected = match (detect_machine_id(), checked_pid()) {
+            (None, _) => Err(GeneratorInitError::MachineIdUnavailable),
+            (Some(_), Err(_)) => Err(GeneratorInitError::PidContainerCheckFailed),
+            (Some(machine_id), Ok(pid)) => Ok((machine_id, fold_pid(pid))),
+        };
+
+        assert_eq!(
+            new_generator_checked().map(|g| (g.machine_id(), g.pid())),
+            expected
+        );
+
+        // the cpuset is only checked on Linux, where it is read
+        if cfg!(target_os = "linux") {
+            assert_eq!(checked_pid().is_ok(), fs::read("/proc/self/cpuset").is_ok());
+        } else {
+            assert_eq!(checked_pid().unwrap(), process::id());
+        }
+
+        if detect_machine_id().is_some() && fs::read("/proc/self/cpuset").is_ok() {
+            assert!(new_generator_checked().unwrap().new_id().is_ok());
+        }
+
+        assert_eq!(
+            GeneratorInitError::MachineIdUnavailable.to_string(),
+            "machine id could not be detected"
+        );
+    }
+
+    #[test]
+    fn test_read_first_valid() {
+        let dir = std::env::temp_dir().join(format!("libxid-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let path = |name: &str| dir.join(name).to_str().unwrap().to_string();
+
+        let (missing, empty, truncated, first, second) = (
+            path("missing"),
+            path("empty"),
+            path("truncated"),
+            path("first"),
+            path("second"),
+        );
+
+        fs::write(&empty, "").unwrap();
+        fs::write(&truncated, "4c4c4544\n").unwrap();
+        fs::write(&first, "0123456789abcdef0123456789abcdef\n").unwrap();
+        fs::write(&second, "4c4c4544-0042-3510-8052-b7c04f4b4d32\n").unwrap();
+
+        assert_eq!(
+            read_first_valid(&[&missing, &empty, &truncated, &first, &second]).unwrap(),
+            "0123456789abcdef0123456789abcdef\n"
+        );
+        assert_eq!(
+            read_first_valid(&[&second, &first]).unwrap(),
+            "4c4c4544-0042-3510-8052-b7c04f4b4d32\n"
+        );
+        assert!(read_first_valid(&[&missing, &empty, &truncated]).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_valid_platform_id() {
+        for invalid in &[
+            "",
+            " \n\t",
+            "4c4c4544-0042-3510",
+            "   0123456789abcdef0123456789abcde  ",
+        ] {
+            assert_eq!(valid_platform_id(invalid), None, "{:?}", invalid);
+        }
+
+        assert_eq!(
+            valid_platform_id(" 0123456789abcdef0123456789abcdef\n"),
+            Some("0123456789abcdef0123456789abcdef")
+        );
+        assert_eq!(
+            valid_platform_id("4C4C4544-0042-3510-8052-B7C04F4B4D32"),
+            Some("4C4C4544-0042-3510-8052-B7C04F4B4D32")
+        );
+    }
+
+    #[test]
+    fn test_parse_platform_machine_id() {
+        let ioreg = r#"+-o MacBookPro  <class IOPlatformExpertDevice, id 0x100000110>
+    {
+      "IOPlatformSerialNumber" = "C02XXXXXXXXX"
+      "IOPlatformUUID" = "1B2C3D4E-5F60-7182-93A4-B5C6D7E8F901"
+    }
+"#;
+        assert_eq!(
+            parse_ioreg_uuid(ioreg).unwrap(),
+            "1B2C3D4E-5F60-7182-93A4-B5C6D7E8F901"
+        );
+        assert!(parse_ioreg_uuid("").is_none());
+    }
+
+    #[test]
+    fn test_clone_generator() {
+        let g = GeneratorBuilder::new().counter(42).build();
+        g.new_id_with_timestamp(1_600_000_000).unwrap();
+
+        let fork = g.clone();
+
+        let a = g.new_id_with_timestamp(1_600_000_000).unwrap();
+        let b = fork.new_id_with_timestamp(1_600_000_000).unwrap();
+
+        assert_eq!(a, b);
+        assert_eq!(b.counter(), 43);
+
+        let fork = g.clone().with_pid_bytes([0xca, 0xfe]);
+
+        let c = g.new_id_with_timestamp(1_600_000_000).unwrap();
+        let d = fork.new_id_with_timestamp(1_600_000_000).unwrap();
+
+        assert_ne!(c, d);
+        assert_eq!(c.counter(), d.counter());
+        assert_eq!(c.machine(), d.machine());
+    }
+
+    #[test]
+    fn test_age() {
+        let id = ID::from_parts(1_600_000_000, [0; 3], 0, 0);
+        let now = UNIX_EPOCH + Duration::from_secs(1_600_000_090);
+
+        assert_eq!(id.age_since(now).unwrap(), Duration::from_secs(90));
+        assert_eq!(id.age_since(id.time()).unwrap(), Duration::from_secs(0));
+        assert!(id.age_since(now - Duration::from_secs(100)).is_err());
+
+        let id = new_generator().new_id().unwrap();
+        assert!(id.age().unwrap() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_encode_into() {
+        let g = new_generator();
+        let mut buff = [0u8; 20];
+
+        for _ in 0..100 {
+            let id = g.new_id().unwrap();
+
+            id.encode_into(&mut buff);
+            assert_eq!(&buff[..], id.encode().as_bytes());
+            assert_eq!(id.encode_str(&mut buff), id.encode());
+        }
+
+        assert_eq!(ID::nil().encode_str(&mut buff), "00000000000000000000");
+    }
+
+    #[test]
+    fn test_counter_rollover_carry() {
+        let g = GeneratorBuilder::new().counter(0).build();
+
+        let ts = 1_500_000_000;
+        let total = COUNTER_MAX + 32;
+
+        let mut previous = g.new_id_with_timestamp(ts).unwrap();
+
+        for _ in 1..total {
+            let id = g.new_id_with_timestamp(ts).unwrap();
+
+            assert!(previous < id, "{:?} >= {:?}", previous, id);
+
+            previous = id;
+        }
+
+        // the ids which did not fit in `ts` were carried to the next second,
+        // which keeps being used while it is ahead of the requested one
+        assert_eq!(previous.timestamp(), ts + 1);
+        assert_eq!(g.new_id_with_timestamp(ts + 1).unwrap().timestamp(), ts + 1);
+        assert_eq!(g.new_id_with_timestamp(ts + 2).unwrap().timestamp(), ts + 2);
+
+        // older seconds are not affected
+        assert_eq!(
+            g.new_id_with_timestamp(ts - 10).unwrap().timestamp(),
+            ts - 10
+        );
+    }
+
+    #[test]
+    fn test_eq_str_symmetric() {
+        let id = ID::decode("9m4e2mr0ui3e8a215n4g").unwrap();
+
+        assert!(id == "9m4e2mr0ui3e8a215n4g");
+        assert!("9m4e2mr0ui3e8a215n4g" == id);
+        assert!(*"9m4e2mr0ui3e8a215n4g" == id);
+        assert!(id.encode() == id);
+
+        assert!(id != "9m4e2mr0ui3e8a215n4h");
+        assert!("invalid" != id);
+        assert!(String::new() != id);
+    }
+
+    #[test]
+    fn test_iter() {
+        let g = new_generator();
+
+        let ids: Vec<ID> = g.iter().take(100).collect();
+
+        assert_eq!(ids.len(), 100);
+        assert!(ids.windows(2).all(|w| w[0] < w[1]));
+
+        let next = g.iter_results().next().unwrap().unwrap();
+        assert!(ids[99] < next);
+
+        for (i, id) in (0..3).zip(g.iter()) {
+            assert_eq!(id.machine(), g.machine_id, "{}", i);
+        }
+    }
+
+    #[test]
+    fn test_as_ref() {
+        let id = new_generator().new_id().unwrap();
+
+        assert_eq!(id.as_ref(), &id.as_bytes()[..]);
+
+        let mut sink = Vec::new();
+        sink.write_all(id.as_ref()).unwrap();
+        assert_eq!(ID::try_from(sink.as_slice()).unwrap(), id);
+
+        let mut hasher = Hasher::new();
+        hasher.update(id.as_ref());
+        assert_eq!(hasher.finalize(), crc32fast::hash(id.as_bytes()));
+    }
+
+    #[test]
+    fn test_error() {
+        let g = new_generator();
+
+        let before_epoch = UNIX_EPOCH - Duration::from_secs(1);
+
+        match g.new_id_with_time(before_epoch) {
+            Err(e @ Error::SystemTime(_)) => assert!(e.source().is_some()),
+            x => panic!("unexpected {:?}", x),
+        }
+
+        let now = SystemTime::now();
+
+        match g.generate_spanning(now, now - Duration::from_secs(10), 2) {
+            Err(e @ Error::InvalidRange) => {
+                assert_eq!(e.to_string(), "end is before start");
+                assert!(e.source().is_none());
+            },
+            x => panic!("unexpected {:?}", x),
+        }
+    }
+
+    #[test]
+    fn test_encode_upper() {
+        let id = ID::decode("9m4e2mr0ui3e8a215n4g").unwrap();
+
+        let upper = DecodeOptions::new().uppercase(true);
+
+        assert_eq!(id.encode_upper(), "9M4E2MR0UI3E8A215N4G");
+        assert_eq!(ID::decode_with(&id.encode_upper(), upper), Ok(id));
+        assert_eq!(ID::decode_with("9M4e2mr0ui3E8A215n4g", upper), Ok(id));
+        assert_eq!(
+            ID::decode_with("9M4E2MR0UI3E8A215N4H", upper),
+            Err(DecodeError::NonCanonical)
+        );
+
+        // not canonical
+        assert!(ID::decode(&id.encode_upper()).is_err());
+        assert!(!is_valid("9M4E2MR0UI3E8A215N4G"));
+
+        let g = new_generator();
+
+        for _ in 0..1000 {
+            let id = g.new_id().unwrap();
+            assert_eq!(ID::decode_with(&id.encode_upper(), upper), Ok(id));
+        }
+    }
+
+    #[test]
+    fn test_sortable_key() {
+        let mut rng = thread_rng();
+        let g = new_generator();
+
+        let mut ids: Vec<ID> = (0..1000).map(|_| g.new_id().unwrap()).collect();
+
+        for _ in 0..10_000 {
+            let mut bytes = [0u8; 12];
+            rng.fill_bytes(&mut bytes);
+            ids.push(ID::from(bytes));
+        }
+
+        ids.shuffle(&mut rng);
+
+        let mut by_id = ids.clone();
+        by_id.sort();
+
+        let mut by_key = ids;
+        by_key.sort_by_key(ID::sortable_key);
+
+        assert_eq!(by_id, by_key);
+
+        for w in by_id.windows(2) {
+            assert_eq!(
+                w[0].cmp(&w[1]),
+                w[0].sortable_key().cmp(&w[1].sortable_key())
+            );
+        }
+    }
+
+    #[test]
+    fn test_generator_accessors() {
+        let g = GeneratorBuilder::new()
+            .machine_id([1, 2, 3])
+            .pid(0xbeef)
+            .build();
+
+        assert_eq!(g.machine_id(), [1, 2, 3]);
+        assert_eq!(g.pid(), 0xbeef);
+
+        let g = new_generator();
+        let id = g.new_id().unwrap();
+
+        assert_eq!(g.machine_id(), id.machine());
+        assert_eq!(g.pid(), id.pid());
+    }
+
+    #[test]
+    fn test_fold_pid() {
+        assert_eq!(fold_pid(1234), 1234);
+        assert_eq!(fold_pid(0xFFFF), 0xFFFF);
+        assert_eq!(fold_pid(0x1234_5678), 0x1234 ^ 0x5678);
+
+        // pids xor-ed with checksums which only differ in their upper half
+        let pids: Vec<u32> = (0..256u32)
+            .flat_map(|checksum| (1..64u32).map(move |pid| pid ^ checksum << 16))
+            .collect();
+
+        let folded: HashSet<u16> = pids.iter().map(|pid| fold_pid(*pid)).collect();
+        let truncated: HashSet<u16> = pids.iter().map(|pid| *pid as u16).collect();
+
+        assert_eq!(truncated.len(), 63);
+        assert!(folded.len() > truncated.len());
+    }
+
+    #[test]
+    fn test_decode_arbitrary_input() {
+        let mut rng = thread_rng();
+
+        let mut inputs: Vec<String> = vec![
+            String::new(),
+            "\0".repeat(20),
+            "\0".repeat(24),
+            "é".repeat(10),
+            "9m4e2mr0ui3e8a215n4\0".to_string(),
+            "9m4e2mr0ui3e8a215n4é".to_string(),
+            "9m4e2mr0ui3e8a215n4gg".to_string(),
+        ];
+
+        for _ in 0..20_000 {
+            let len = rng.gen_range(0, 42);
+            let mut bytes = vec![0u8; len];
+            rng.fill_bytes(&mut bytes);
+
+            // mostly chars of the alphabet, so that some inputs get past the
+            // length and character checks
+            if rng.gen() {
+                for b in bytes.iter_mut().filter(|_| rng.gen_range(0, 8) != 0) {
+                    *b = ENCODING[*b as usize % 32];
+                }
+            }
+
+            inputs.push(String::from_utf8_lossy(&bytes).into_owned());
+        }
+
+        for input in &inputs {
+            match ID::decode(input) {
+                Ok(id) => assert_eq!(&id.encode(), input),
+                Err(_) => assert!(!is_valid(input)),
+            }
+
+            assert_eq!(input.parse::<ID>().is_ok(), is_valid(input));
+            let _ = ID::from_mongo_object_id_hex(input);
+            let _ = input.parse::<IdList>();
+            assert_eq!(ID::try_from(input.as_str()).is_ok(), is_valid(input));
+        }
+    }
+
+    #[test]
+    fn test_parse_timestamp() {
+        let mut rng = thread_rng();
+
+        for _ in 0..10_000 {
+            let mut bytes = [0u8; 12];
+            rng.fill_bytes(&mut bytes);
+
+            let id = ID::from(bytes);
+
+            assert_eq!(parse_timestamp(&id.encode()), Ok(id.timestamp()));
+        }
+
+        assert_eq!(
+            parse_timestamp("9M4E2MR0UI3E8A215N4G"),
+            Err(DecodeError::InvalidCharacter {
+                position: 1,
+                character: 'M'
+            })
+        );
+
+        let id = new_generator().new_id().unwrap();
+        assert_eq!(parse_timestamp(&id.encode()), Ok(id.timestamp()));
+
+        assert_eq!(
+            parse_timestamp("9m4e2mr0ui3e8a215n4"),
+            Err(DecodeError::InvalidLength {
+                expected: 20,
+                actual: 19
+            })
+        );
+        assert_eq!(
+            parse_timestamp("9m4e2mw0ui3e8a215n4g"),
+            Err(DecodeError::InvalidCharacter {
+                position: 6,
+                character: 'w',
+            })
+        );
+    }
+
+    #[test]
+    fn test_error_display() {
+        fn parse(s: &str) -> Result<ID, Box<dyn StdError>> {
+            Ok(s.parse::<ID>()?)
+        }
+
+        fn generate(t: SystemTime) -> Result<ID, Box<dyn StdError>> {
+            Ok(new_generator().new_id_with_time(t)?)
+        }
+
+        assert!(parse("9m4e2mr0ui3e8a215n4g").is_ok());
 
-fn rand_int() -> AtomicUsize {
-    let mut buff = [0u8; 3];
+        assert_eq!(
+            parse("9m4e2mr0ui3e8a215n").unwrap_err().to_string(),
+            "invalid length: expected 20 bytes, got 18"
+        );
+        assert_eq!(
+            parse("9m4e2mr0ui3e8a215n4w").unwrap_err().to_string(),
+            "invalid character 'w' at position 19"
+        );
+        assert_eq!(
+            "9m4e2mrzui3e8a215n4g"
+                .parse::<ID>()
+                .unwrap_err()
+                .to_string(),
+            "invalid character 'z' at position 7"
+        );
+        assert_eq!(
+            parse("9m4e2mr0ui3e8a215n4h").unwrap_err().to_string(),
+            "non canonical encoding"
+        );
+        assert_eq!(
+            ID::try_from(&[0u8; 11][..]).unwrap_err().to_string(),
+            "invalid length: expected 12 bytes, got 11"
+        );
 
-    thread_rng().fill_bytes(&mut buff);
+        let err = generate(UNIX_EPOCH - Duration::from_secs(1)).unwrap_err();
+        assert!(err.source().is_some());
+        assert_eq!(
+            Error::CounterExhausted.to_string(),
+            "counter exhausted for the current second"
+        );
+        assert_eq!(Error::Rng.to_string(), "os random source unavailable");
+        assert!(Error::Rng.source().is_none());
+    }
 
-    let x = (buff[0] as usize) << 16 | (buff[1] as usize) << 8 | buff[2] as usize;
+    #[test]
+    fn test_reset_counter() {
+        let g = Generator::from_seed(42);
 
-    AtomicUsize::new(x)
-}
+        let ts = 1_500_000_000;
 
-fn get_pid() -> u32 {
-    let mut pid = process::id();
+        let first: Vec<ID> = (0..10)
+            .map(|_| g.new_id_with_timestamp(ts).unwrap())
+            .collect();
 
-    // If /proc/self/cpuset exists and is not /, we can assume that we are in a
-    // form of container and use the content of cpuset xor-ed with the PID in
-    // order get a reasonable machine global unique PID.
-    match fs::read("/proc/self/cpuset") {
-        Err(_) => pid,
+        g.reset_counter(first[0].counter());
 
-        Ok(buff) => {
-            let mut hasher = Hasher::new();
-            hasher.update(buff.as_slice());
-            let checksum = hasher.finalize();
+        let replayed: Vec<ID> = (0..10)
+            .map(|_| g.new_id_with_timestamp(ts).unwrap())
+            .collect();
 
-            pid ^= checksum;
+        assert_eq!(first, replayed);
 
-            pid
-        }
+        g.reset_counter(0x1FF_FFFF);
+        assert_eq!(g.new_id_with_timestamp(ts).unwrap().counter(), 0xFF_FFFF);
     }
-}
 
-fn read_machine_id() -> [u8; 3] {
-    let id = match platform_machine_id() {
-        // XXX: https://github.com/rust-lang/rfcs/blob/master/text/0107-pattern-guards-with-bind-by-move.md
-        Ok(x) => {
-            if !x.is_empty() {
-                x
-            } else {
-                hostname()
-            }
-        }
+    #[test]
+    fn test_same_source() {
+        let g = GeneratorBuilder::new().machine_id([1, 2, 3]).pid(4).build();
 
-        _ => hostname(),
-    };
+        let a = g.new_id_with_timestamp(1_500_000_000).unwrap();
+        let b = g.new_id_with_timestamp(1_600_000_000).unwrap();
 
-    if id.is_empty() {
-        let mut buff = [0u8; 3];
-        thread_rng().fill_bytes(&mut buff);
-        return buff;
-    }
+        assert!(a.same_source(&b));
+        assert!(a.same_source(&ID::from_parts(0, [1, 2, 3], 4, 0)));
 
-    let hash = md5::compute(id);
+        let other = g.clone().with_pid_bytes([0, 5]);
+        assert!(!a.same_source(&other.new_id_with_timestamp(1_500_000_000).unwrap()));
 
-    [hash[0], hash[1], hash[2]]
-}
+        let other = g.clone().with_machine_id([1, 2, 4]);
+        assert!(!a.same_source(&other.new_id_with_timestamp(1_500_000_000).unwrap()));
+    }
 
-#[cfg(target_os = "linux")]
-fn platform_machine_id() -> Result<String, io::Error> {
-    // XXX: unlikely to work if read with an unpriviledged user
-    let mut file = File::open("/sys/class/dmi/id/product_uuid")?;
+    #[cfg(unix)]
+    #[test]
+    fn test_hostname_string_non_utf8() {
+        use std::ffi::OsString;
+        use std::os::unix::ffi::OsStringExt;
 
-    let mut contents = String::new();
+        assert_eq!(hostname_string(OsString::from("host-1")), "host-1");
 
-    file.read_to_string(&mut contents)?;
+        let name = hostname_string(OsString::from_vec(b"host-\xFF\xFE".to_vec()));
+        assert!(name.starts_with("host-"));
+        assert_ne!(name, "host-");
+    }
 
-    Ok(contents)
-}
+    #[test]
+    fn test_object_id() {
+        // example Object ID of the Mongo documentation, created on
+        // 2012-10-17T21:13:27Z
+        let bytes = [
+            0x50, 0x7f, 0x1f, 0x77, 0xbc, 0xf8, 0x6c, 0xd7, 0x99, 0x43, 0x90, 0x11,
+        ];
+
+        let id = ID::from_object_id(bytes);
+
+        assert_eq!(id.timestamp(), 1_350_508_407);
+        assert_eq!(id.counter(), 0x43_9011);
+        assert_eq!(id.to_object_id(), bytes);
+        assert_eq!(id.to_mongo_object_id_hex(), "507f1f77bcf86cd799439011");
+        assert_eq!(
+            ID::from_mongo_object_id_hex("507f1f77bcf86cd799439011"),
+            Ok(id)
+        );
 
-fn hostname() -> String {
-    gethostname()
-        .into_string()
-        .expect("can not fetch machine's hostname")
-}
+        let id = new_generator().new_id().unwrap();
+        assert_eq!(ID::from_object_id(id.to_object_id()), id);
+    }
 
-// ---
+    #[test]
+    fn test_min_max_for_time() {
+        let t = UNIX_EPOCH + Duration::from_millis(1_500_000_000_500);
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::collections::HashMap;
-    use std::time::Instant;
+        let min = ID::min_for_time(t);
+        let max = ID::max_for_time(t);
 
-    #[test]
-    fn test_basic() {
-        let total = 1e6 as u32;
+        assert_eq!(min.timestamp(), 1_500_000_000);
+        assert_eq!(max.timestamp(), 1_500_000_000);
+        assert_eq!(&min.as_bytes()[4..], &[0; 8]);
+        assert_eq!(&max.as_bytes()[4..], &[0xFF; 8]);
 
         let g = new_generator();
+        let id = g.new_id_with_time(t).unwrap();
 
-        let mut previous_counter = 0;
-        let mut previous_id = g.new_id().unwrap();
+        assert!(min < id && id < max);
+        assert!(ID::max_for_time(t - Duration::from_secs(1)) < min);
+        assert!(max < ID::min_for_time(t + Duration::from_secs(1)));
 
-        for i in 0..total {
-            let id = g.new_id().unwrap();
+        assert_eq!(
+            ID::min_for_time(UNIX_EPOCH - Duration::from_secs(1)),
+            ID::nil()
+        );
 
-            assert!(
-                previous_id < id,
-                format!(
-                    "{} ({:?}) != {} ({:?}) {}",
-                    previous_id.encode(),
-                    previous_id,
-                    id.encode(),
-                    id,
-                    i
-                )
-            );
+        // past 2106 the bounds stick to the last second instead of wrapping
+        let after = UNIX_EPOCH + Duration::from_secs((1 << 32) + 10);
+        assert_eq!(ID::max_for_time(after), ID::from_bytes([0xFF; ID_LEN]));
+        assert_eq!(ID::min_for_time(after).timestamp(), u32::MAX);
+        assert!(ID::max_for_time(t) < ID::max_for_time(after));
+    }
 
-            if i > 0 {
-                assert_eq!(id.counter(), previous_counter + 1);
-            }
+    proptest! {
+        #[test]
+        fn test_prop_bytes_round_trip(bytes in any::<[u8; 12]>()) {
+            let id = ID::from_bytes(bytes);
 
-            previous_counter = id.counter();
+            prop_assert_eq!(ID::from_bytes(*id.as_bytes()), id);
+            prop_assert_eq!(id.as_bytes(), &bytes);
+        }
 
-            let x = id.encode();
-            assert_eq!(x.len(), 20);
-            assert_eq!(id.machine(), g.machine_id);
+        #[test]
+        fn test_prop_encode_round_trip(bytes in prop_oneof![
+            Just([0u8; 12]),
+            Just([0xFFu8; 12]),
+            any::<[u8; 12]>(),
+        ]) {
+            let id = ID::from_bytes(bytes);
+            let encoded = id.encode();
+
+            prop_assert_eq!(encoded.len(), 20);
+            prop_assert!(is_valid(&encoded));
+            prop_assert_eq!(ID::decode(&encoded), Ok(id));
+        }
 
-            previous_id = id;
+        #[test]
+        fn test_prop_encoded_order(a in any::<[u8; 12]>(), b in any::<[u8; 12]>()) {
+            let (a, b) = (ID::from_bytes(a), ID::from_bytes(b));
+
+            prop_assert_eq!(a.cmp(&b), a.encode().cmp(&b.encode()));
         }
     }
 
     #[test]
-    fn test_generation_speed() {
-        let total = 1e6 as u32;
-
-        let g = new_generator();
+    fn test_hex_format() {
+        let id = ID::decode("9m4e2mr0ui3e8a215n4g").unwrap();
+
+        assert_eq!(format!("{:x}", id), "4d88e15b60f486e428412dc9");
+        assert_eq!(format!("{:X}", id), "4D88E15B60F486E428412DC9");
+        assert_eq!(format!("{:x}", ID::nil()), "0".repeat(24));
+
+        let id = new_generator().new_id().unwrap();
+        assert_eq!(format!("{:x}", id), id.to_mongo_object_id_hex());
+        assert_eq!(
+            format!("{:X}", id),
+            id.to_mongo_object_id_hex().to_uppercase()
+        );
+    }
 
-        let start = Instant::now();
+    #[test]
+    fn test_parse_machine_id() {
+        assert_eq!(parse_machine_id("0a1B2c"), Some([0x0a, 0x1b, 0x2c]));
+        assert_eq!(parse_machine_id(" ffffff\n"), Some([0xFF; 3]));
 
-        for _ in 0..total {
-            g.new_id().unwrap();
+        for invalid in &["", "0a1b2", "0a1b2c3", "+a1b2c", "0a1b2g", "0x1b2c"] {
+            assert_eq!(parse_machine_id(invalid), None, "{:?}", invalid);
         }
+    }
 
-        let elapsed =
-            start.elapsed().as_secs() as f64 + start.elapsed().subsec_nanos() as f64 * 1e-9;
+    #[test]
+    fn test_refresh_pid() {
+        // a generator inherited from a parent process has a stale pid
+        let g = new_generator().with_pid_bytes((get_pid() ^ 1).to_be_bytes());
+        let before = g.new_id().unwrap();
 
-        let limit = 0.5;
+        g.refresh_pid();
 
-        assert!(
-            elapsed <= limit,
-            format!(
-                "Must generated {} ids id less than {} second, took {} seconds",
-                total, limit, elapsed
-            )
-        );
+        let after = g.new_id().unwrap();
+
+        assert_eq!(g.pid(), get_pid());
+        assert_eq!(after.pid(), get_pid());
+        assert_ne!(before.pid(), after.pid());
     }
 
     #[test]
-    fn test_encoding_speed() {
-        let total = 1e6 as u32;
+    fn test_machine_id_from_str() {
+        // md5("worker-eu-west-3") starts with f9 97 34
+        assert_eq!(machine_id_from_str("worker-eu-west-3"), [0xf9, 0x97, 0x34]);
+        assert_ne!(
+            machine_id_from_str("worker-eu-west-3"),
+            machine_id_from_str("worker-eu-west-2")
+        );
 
-        let g = new_generator();
+        let id = GeneratorBuilder::new()
+            .machine_id(machine_id_from_str("worker-eu-west-3"))
+            .build()
+            .new_id()
+            .unwrap();
 
-        let mut buff: Vec<ID> = Vec::with_capacity(total as usize);
+        assert_eq!(id.machine(), [0xf9, 0x97, 0x34]);
+    }
 
-        for _ in 0..total {
-            buff.push(g.new_id().unwrap().clone());
-        }
+    #[test]
+    fn test_from_bytes_validated() {
+        let min = UNIX_EPOCH + Duration::from_secs(1_500_000_000);
+        let max = UNIX_EPOCH + Duration::from_secs(1_600_000_000);
 
-        let start = Instant::now();
+        let at = |secs: u32| *ID::from_parts(secs, [1, 2, 3], 4, 5).as_bytes();
 
-        for id in buff.into_iter() {
-            id.encode();
+        for secs in &[1_500_000_000, 1_550_000_000, 1_600_000_000] {
+            assert_eq!(
+                ID::from_bytes_validated(at(*secs), min, max),
+                Ok(ID::from_bytes(at(*secs)))
+            );
         }
 
-        let elapsed =
-            start.elapsed().as_secs() as f64 + start.elapsed().subsec_nanos() as f64 * 1e-9;
+        for secs in &[0, 1_499_999_999, 1_600_000_001, u32::MAX] {
+            assert_eq!(
+                ID::from_bytes_validated(at(*secs), min, max),
+                Err(DecodeError::TimestampOutOfRange)
+            );
+        }
 
-        let limit = 1.5;
+        // a far future bound does not wrap around and reject everything
+        let far = UNIX_EPOCH + Duration::from_secs(7_258_118_400); // 2200-01-01
+        for secs in &[1_500_000_000, 1_600_000_001, u32::MAX] {
+            assert!(ID::from_bytes_validated(at(*secs), min, far).is_ok());
+        }
 
+        // sub-second bounds include their whole second
         assert!(
-            elapsed <= limit,
-            format!(
-                "Must encode {} ids id less than {} second, took {} seconds",
-                total, limit, elapsed
-            )
+            ID::from_bytes_validated(at(1_600_000_000), min, max + Duration::from_millis(999))
+                .is_ok()
         );
     }
 
     #[test]
-    fn test_eq() {
+    fn test_global_new_id() {
+        let first = new_id().unwrap();
+        assert!(first < new_id().unwrap());
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| thread::spawn(|| (0..1000).map(|_| new_id().unwrap()).collect::<Vec<ID>>()))
+            .collect();
+
+        let mut seen = HashSet::new();
+
+        for h in handles {
+            for id in h.join().unwrap() {
+                assert!(first < id);
+                assert!(first.same_source(&id));
+                assert!(seen.insert(id));
+            }
+        }
+    }
+
+    #[test]
+    fn test_encode_to_writer() {
         let g = new_generator();
 
-        let a = g.new_id().unwrap();
-        let b = g.new_id().unwrap();
-        let c = g.new_id().unwrap();
+        let ids: Vec<ID> = (0..100).map(|_| g.new_id().unwrap()).collect();
 
-        assert!(a == a);
-        assert!(a <= a);
-        assert!(a != b);
-        assert!(a != c);
+        let mut out = io::BufWriter::new(Vec::new());
 
-        assert!(a < b);
-        assert!(b > a);
-        assert!(b >= a);
+        for id in &ids {
+            id.encode_to_writer(&mut out).unwrap();
+        }
 
-        assert!(b < c);
-        assert!(c > b);
+        let out = out.into_inner().unwrap();
 
-        assert!(a < c);
-        assert!(c > a);
+        let expected: String = ids.iter().map(ID::encode).collect();
+        assert_eq!(out, expected.as_bytes());
     }
 
     #[test]
-    fn test_from() {
+    fn test_encode_many() {
         let g = new_generator();
 
-        let a = g.new_id().unwrap();
+        let ids: Vec<ID> = (0..10).map(|_| g.new_id().unwrap()).collect();
 
-        let b = ID::from(a.encode().as_str());
+        let packed = encode_many(&ids);
 
-        assert_eq!(a.val, b.val);
-        assert_eq!(a.encode(), b.encode());
+        assert_eq!(packed.len(), 200);
+        assert_eq!(packed, ids.iter().map(ID::encode).collect::<String>());
+        assert_eq!(decode_many(&packed), Ok(ids));
 
-        assert_eq!(ID::from("invalid").val, [0u8; ID_LEN]);
+        assert_eq!(encode_many(&[]), "");
+        assert_eq!(decode_many(""), Ok(vec![]));
+
+        assert_eq!(
+            decode_many("9m4e2mr0ui3e8a215n4g9m4e2mr0ui3e8a215n4"),
+            Err(DecodeError::InvalidLength {
+                expected: 40,
+                actual: 39
+            })
+        );
+        assert_eq!(
+            decode_many("9m4e2mr0ui3e8a215n4g9m4e2mr0ui3e8a215n4w"),
+            Err(DecodeError::InvalidCharacter {
+                position: 39,
+                character: 'w',
+            })
+        );
+        // a multi-byte char split between two ids
+        assert_eq!(
+            decode_many("9m4e2mr0ui3e8a215n4éi3e8a215n4g9m4e2mr0"),
+            Err(DecodeError::InvalidCharacter {
+                position: 19,
+                character: 'é',
+            })
+        );
     }
 
     #[test]
-    fn test_encode_decode() {
-        let total = 1e6 as u32;
+    fn test_within_second() {
+        let a = ID::from_parts(1_500_000_000, [1, 2, 3], 4, 5);
+        let b = ID::from_parts(1_500_000_000, [6, 7, 8], 9, 10);
+        let c = ID::from_parts(1_500_000_001, [1, 2, 3], 4, 6);
 
-        let g = new_generator();
+        assert!(a.within_second(&b));
+        assert!(b.within_second(&a));
+        assert!(!a.within_second(&c));
 
-        for _ in 0..total {
-            let id = g.new_id().unwrap();
+        let mut buckets: BTreeMap<u32, usize> = BTreeMap::new();
 
-            assert_eq!(id, ID::decode(&id.encode()));
+        for id in &[a, b, c] {
+            *buckets.entry(id.second_bucket()).or_default() += 1;
         }
+
+        assert_eq!(
+            buckets.into_iter().collect::<Vec<_>>(),
+            vec![(1_500_000_000, 2), (1_500_000_001, 1)]
+        );
     }
 
     #[test]
-    fn test_decoding_speed() {
-        let total = 1e6 as u32;
+    fn test_ids_generated() {
+        let g = Arc::new(GeneratorBuilder::new().counter(0xFF_FFF0).build());
+
+        assert_eq!(g.ids_generated(), 0);
+
+        g.new_ids(100).unwrap();
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let g = Arc::clone(&g);
+                thread::spawn(move || {
+                    for _ in 0..1000 {
+                        g.new_id().unwrap();
+                    }
+                })
+            })
+            .collect();
+
+        for h in handles {
+            h.join().unwrap();
+        }
 
-        let g = new_generator();
+        // the embedded counter wrapped around, the total did not
+        assert_eq!(g.ids_generated(), 4100);
+        assert!(g.next_counter() < 4100);
 
-        let mut buff: Vec<String> = Vec::with_capacity(total as usize);
+        assert_eq!(g.as_ref().clone().ids_generated(), 0);
+    }
 
-        for _ in 0..total {
-            let id = g.new_id().unwrap();
+    #[test]
+    fn test_new_random_id() {
+        let g = new_generator();
+        let before = SystemTime::now();
 
-            buff.push(id.encode().clone());
+        let ids: Vec<ID> = (0..1000).map(|_| g.new_random_id().unwrap()).collect();
 
-            assert_eq!(id, ID::decode(&id.encode()));
+        let unique: HashSet<&ID> = ids.iter().collect();
+        assert_eq!(unique.len(), ids.len());
+
+        for id in &ids {
+            assert!(id.time() + Duration::from_secs(1) >= before);
+            assert_eq!(ID::decode(&id.encode()), Ok(*id));
         }
 
-        // ----
+        // the random bytes are not derived from the generator
+        assert!(ids.iter().any(|id| id.machine() != g.machine_id()));
+        assert_eq!(g.ids_generated(), 1000);
+    }
+
+    #[test]
+    fn test_new_id_throttled() {
+        let g = new_generator();
 
+        // far from the end of the counter, no waiting
         let start = Instant::now();
+        let a = g.new_id_throttled(1000).unwrap();
+        let b = g.new_id_throttled(1000).unwrap();
+        assert!(start.elapsed() < Duration::from_millis(500));
+        assert!(a < b);
 
-        for encoded in buff.into_iter() {
-            ID::decode(&encoded);
-        }
+        g.reset_counter(0xFF_FFFF - 5);
+        let first = g.new_id().unwrap();
 
-        let elapsed =
-            start.elapsed().as_secs() as f64 + start.elapsed().subsec_nanos() as f64 * 1e-9;
+        let next = g.new_id_throttled(10).unwrap();
+        assert!(next.timestamp() > first.timestamp());
+        assert!(next.time() <= SystemTime::now());
+        // the counter went on in the new second instead of wrapping
+        assert_eq!(next.counter(), first.counter() + 1);
 
-        let limit = 0.5;
+        // exactly `threshold` counters left in the second, no waiting (retried
+        // in case the clock moves to the next second in between)
+        let same_second = (0..3).any(|_| {
+            g.reset_counter(0xFF_FFFF - 10);
+            let first = g.new_id().unwrap();
 
-        assert!(
-            elapsed <= limit,
-            format!(
-                "Must decode {} ids in less than {} second, took {} seconds",
-                total, limit, elapsed
-            )
+            g.new_id_throttled(10).unwrap().timestamp() == first.timestamp()
+        });
+        assert!(same_second);
+    }
+
+    #[test]
+    fn test_from_mongo_hex_to_xid_string() {
+        assert_eq!(
+            from_mongo_hex_to_xid_string("4d88e15b60f486e428412dc9"),
+            Ok("9m4e2mr0ui3e8a215n4g".to_string())
+        );
+        assert_eq!(
+            from_mongo_hex_to_xid_string("4D88E15B60F486E428412DC9"),
+            Ok("9m4e2mr0ui3e8a215n4g".to_string())
+        );
+
+        assert_eq!(
+            from_mongo_hex_to_xid_string("4d88e15b60f486e428412d"),
+            Err(DecodeError::InvalidLength {
+                expected: 24,
+                actual: 22
+            })
+        );
+        assert_eq!(
+            from_mongo_hex_to_xid_string("4d88e15b60f486e42841xdc9"),
+            Err(DecodeError::InvalidCharacter {
+                position: 20,
+                character: 'x'
+            })
         );
     }
 
     #[test]
-    fn test_json() {
+    fn test_machine_id_is_random() {
+        let mut rng = StdRng::seed_from_u64(3);
+
+        assert_eq!(
+            read_machine_id(Some([1, 2, 3]), &mut rng),
+            ([1, 2, 3], false)
+        );
+
+        let (id, random) = read_machine_id(None, &mut rng);
+        assert!(random);
+        assert_ne!(read_machine_id(None, &mut rng).0, id);
+
+        let g = GeneratorBuilder::new().machine_id([1, 2, 3]).build();
+        assert!(!g.machine_id_is_random());
+        assert!(!Generator::from_seed(1).machine_id_is_random());
+
+        let mut g = g;
+        g.machine_id_random = true;
+        assert!(g.clone().machine_id_is_random());
+        assert!(!g.with_machine_id([4, 5, 6]).machine_id_is_random());
+    }
+
+    #[test]
+    fn test_borrow_bytes() {
         let g = new_generator();
+        let ids: Vec<ID> = g.iter().take(10).collect();
 
-        let src = g.new_id().unwrap();
+        // byte keys looked up with ids
+        let keys: BTreeSet<[u8; ID_LEN]> = ids.iter().map(|id| *id.as_bytes()).collect();
+        let hashed: HashSet<[u8; ID_LEN]> = ids.iter().map(|id| *id.as_bytes()).collect();
 
-        let serialized = serde_json::to_string(&src).unwrap();
+        for id in &ids {
+            let key: &[u8; ID_LEN] = id.borrow();
 
-        let deserialized: ID = serde_json::from_str(&serialized).unwrap();
-        assert_eq!(src, deserialized);
+            assert!(keys.contains(key));
+            assert!(hashed.contains(key));
+        }
 
-        let invalid: ID = serde_json::from_str("\"invalid\"").unwrap();
-        assert_eq!(invalid.val, [0u8; ID_LEN]);
+        // id keys looked up with bytes
+        let by_id: BTreeSet<ID> = ids.iter().copied().collect();
+        let hashed: HashSet<ID> = ids.iter().copied().collect();
+
+        for id in &ids {
+            assert!(by_id.contains(id.as_bytes()));
+            assert!(hashed.contains(id.as_bytes()));
+        }
+
+        assert!(!by_id.contains(&[0u8; ID_LEN]));
+        assert_eq!(
+            by_id.iter().map(|id| *id.as_bytes()).collect::<Vec<_>>(),
+            keys.into_iter().collect::<Vec<_>>()
+        );
     }
 
     #[test]
-    fn test_hash_map_reference_no_encode() {
+    fn test_new_ids_with_time() {
+        let g = Generator::from_seed(7);
+        let t = UNIX_EPOCH + Duration::from_secs(1_560_604_800);
+
+        let encoded: Vec<String> = g
+            .new_ids_with_time(3, t)
+            .unwrap()
+            .iter()
+            .map(|id| id.encode())
+            .collect();
+
+        assert_eq!(
+            encoded,
+            vec![
+                "bk2f1073s4o17dqdgskg",
+                "bk2f1073s4o17dqdgsl0",
+                "bk2f1073s4o17dqdgslg"
+            ]
+        );
+
+        g.reset_counter(0xFF_FFFE);
+        let ids = g.new_ids_with_time(4, t).unwrap();
+
+        assert_eq!(
+            ids.iter()
+                .map(|id| (id.timestamp(), id.counter()))
+                .collect::<Vec<_>>(),
+            vec![
+                (1_560_604_800, 0xFF_FFFE),
+                (1_560_604_800, 0xFF_FFFF),
+                (1_560_604_801, 0),
+                (1_560_604_801, 1),
+            ]
+        );
+
+        assert!(g
+            .new_ids_with_time(1, UNIX_EPOCH - Duration::from_secs(1))
+            .is_err());
+    }
+
+    #[test]
+    fn test_write_packed() {
         let g = new_generator();
-        let id = g.new_id().unwrap();
-        let mut map: HashMap<&ID, String> = HashMap::new();
+        let ids: Vec<ID> = g.iter().take(5).collect();
 
-        map.insert(&id, String::from("test"));
-        let value = map.get(&id);
+        let mut buff = Vec::new();
+        for id in &ids {
+            id.write_packed(&mut buff).unwrap();
+        }
 
-        assert!(value.is_some());
-        assert_eq!("test", value.unwrap());
+        assert_eq!(buff.len(), 5 * ID_LEN);
+        assert_eq!(&buff[..ID_LEN], ids[0].as_bytes());
+
+        let mut r = io::Cursor::new(buff);
+        for id in &ids {
+            assert_eq!(ID::read_packed(&mut r).unwrap(), *id);
+        }
+
+        assert_eq!(
+            ID::read_packed(&mut r).unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+
+        let id = ID::from_bytes([1; ID_LEN]);
+        let mut buff = Vec::new();
+        id.write_packed(&mut buff).unwrap();
+        assert_eq!(buff, vec![1; ID_LEN]);
     }
 
     #[test]
-    fn test_hash_map_reference_encode() {
+    fn test_decode_with() {
+        let id = ID::decode("9m4e2mr0ui3e8a215n4g").unwrap();
+
+        let strict = DecodeOptions::new();
+        let upper = DecodeOptions::new().uppercase(true);
+        let padded = DecodeOptions::new().padding(true);
+        let lenient = DecodeOptions::new().uppercase(true).padding(true);
+
+        for opts in &[strict, upper, padded, lenient] {
+            assert_eq!(ID::decode_with("9m4e2mr0ui3e8a215n4g", *opts), Ok(id));
+            assert_eq!(
+                ID::decode_with("9m4e2mr0ui3e8a215n4w", *opts),
+                Err(DecodeError::InvalidCharacter {
+                    position: 19,
+                    character: 'w'
+                })
+            );
+        }
+
+        let check = |input: &str, accepted: &[DecodeOptions]| {
+            for opts in &[strict, upper, padded, lenient] {
+                let res = ID::decode_with(input, *opts);
+
+                if accepted.contains(opts) {
+                    assert_eq!(res, Ok(id), "{} {:?}", input, opts);
+                } else {
+                    assert!(res.is_err(), "{} {:?}", input, opts);
+                }
+            }
+        };
+
+        check("9M4E2MR0UI3E8A215N4G", &[upper, lenient]);
+        check("9m4E2mr0Ui3e8a215n4G", &[upper, lenient]);
+        check("9m4e2mr0ui3e8a215n4g====", &[padded, lenient]);
+        check("9M4e2MR0ui3e8a215n4g====", &[lenient]);
+        check("9m4e2mr0ui3e8a215n4g=", &[]);
+        check("9m4e2mr0ui3e8a215n4g===", &[]);
+        check("9m4e2mr0ui3e8a215n4g========", &[]);
+        check("9m4e2mr0ui3e8a2=15n4g", &[]);
+        check("====", &[]);
+
+        assert_eq!(
+            ID::decode_with("9m4e2mr0Ui3e8a215n4g", strict),
+            Err(DecodeError::InvalidCharacter {
+                position: 8,
+                character: 'U'
+            })
+        );
+        assert_eq!(
+            ID::decode_with("9m4e2mr0ui3e8a215n4g====", strict),
+            Err(DecodeError::InvalidLength {
+                expected: 20,
+                actual: 24
+            })
+        );
+
+        assert_eq!(
+            ID::decode_with("9m4e2mr0uW3e8a215n4g", upper),
+            Err(DecodeError::InvalidCharacter {
+                position: 9,
+                character: 'W'
+            })
+        );
+
+        // same as the strict options
+        assert!(ID::decode("9M4E2MR0UI3E8A215N4G").is_err());
+        assert!(ID::decode("9m4e2mr0ui3e8a215n4g====").is_err());
+    }
+
+    #[test]
+    fn test_with_pid_override() {
+        let g = Generator::with_pid_override(0xBEEF);
+
+        assert_eq!(g.pid(), 0xBEEF);
+        assert_eq!(g.new_id().unwrap().pid(), 0xBEEF);
+        assert_eq!(g.machine_id(), new_generator().machine_id());
+    }
+
+    #[test]
+    fn test_generate_spanning_after_wrap() {
         let g = new_generator();
-        let id = g.new_id().unwrap();
-        let mut map: HashMap<&ID, String> = HashMap::new();
 
-        let encoded = id.encode();
-        map.insert(&id, String::from("test"));
+        let t = UNIX_EPOCH + Duration::from_secs(1_500_000_000);
+        let end = t + Duration::from_secs(10);
 
-        let id = ID::decode(&encoded);
-        let value = map.get(&id);
+        // the second id is carried to t+1
+        g.reset_counter(0xFF_FFFF);
+        g.new_id_with_time(t).unwrap();
+        assert_eq!(
+            g.new_id_with_time(t).unwrap().time(),
+            t + Duration::from_secs(1)
+        );
 
-        assert!(value.is_some());
-        assert_eq!("test", value.unwrap());
+        let ids = g.generate_spanning(t, end, 3).unwrap();
+        for id in &ids {
+            assert!(id.time() >= t && id.time() <= end);
+        }
+
+        g.reset_counter(0xFF_FFFF);
+        g.new_id_with_time(t).unwrap();
+        g.new_id_with_time(t).unwrap();
+
+        // no id fits in the single, exhausted, second
+        match g.generate_spanning(t, t, 3) {
+            Err(Error::CounterExhausted) => {},
+            x => panic!("unexpected result {:?}", x),
+        }
     }
 
     #[test]
-    fn test_hash_map_no_reference() {
+    fn test_new_id_after_older_explicit_time() {
         let g = new_generator();
-        let id = g.new_id().unwrap();
-        let mut map: HashMap<ID, String> = HashMap::new();
 
-        let encoded = id.encode();
-        map.insert(id, String::from("test"));
+        let now = SystemTime::now();
 
-        let id = ID::decode(&encoded);
-        let value = map.get(&id);
+        g.reset_counter(0xFF_FFFF);
+        g.new_id_with_time(now).unwrap();
 
-        assert!(value.is_some());
-        assert_eq!("test", value.unwrap());
+        // carried to the next second
+        let b = g.new_id().unwrap();
+
+        g.new_id_with_time(now - Duration::from_secs(3600)).unwrap();
+        g.new_ids_with_time(3, now - Duration::from_secs(60))
+            .unwrap();
+
+        let c = g.new_id().unwrap();
+        assert!(c > b);
+
+        let ids = g.new_ids(3).unwrap();
+        assert!(ids[0] > c);
+    }
+
+    #[test]
+    fn test_try_build() {
+        let g = GeneratorBuilder::new()
+            .machine_id([1, 2, 3])
+            .pid(4)
+            .try_build()
+            .unwrap();
+        assert_eq!((g.machine_id(), g.pid()), ([1, 2, 3], 4));
+
+        assert_eq!(
+            GeneratorBuilder::new()
+                .try_build()
+                .map(|g| (g.machine_id(), g.pid())),
+            new_generator_checked().map(|g| (g.machine_id(), g.pid()))
+        );
     }
 }